@@ -1,3 +1,4 @@
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum AcsType {
     GenieAcs,
 }
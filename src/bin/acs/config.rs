@@ -0,0 +1,125 @@
+//! Connection settings from the command line, the environment and the
+//! config file, in that order of precedence.
+
+use acs_api_rs::acs_type::AcsType;
+use acs_api_rs::connection::AcsConnection;
+use clap::Args;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Contents of the config file, `$XDG_CONFIG_HOME/acs/config.toml` or
+/// `~/.config/acs/config.toml` unless `--config` says otherwise:
+///
+/// ```toml
+/// url = "http://genieacs:7557"
+/// username = "admin"
+/// password = "secret"
+/// ca_cert = "/etc/ssl/acs-ca.pem"
+/// timeout = 30
+/// file_server = "http://genieacs:7567"
+/// ```
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+    pub ca_cert: Option<PathBuf>,
+    /// Request timeout in seconds
+    pub timeout: Option<u64>,
+    pub file_server: Option<String>,
+}
+
+impl Config {
+    fn default_path() -> Option<PathBuf> {
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        return Some(base.join("acs").join("config.toml"));
+    }
+
+    /// Reads `path`, or the default config file if it exists.
+    pub fn load(path: Option<&PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = match path {
+            Some(path) => path.clone(),
+            None => match Config::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let config =
+            toml::from_str(&text).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        return Ok(config);
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ConnectionArgs {
+    /// Address of the GenieACS NBI, e.g. http://genieacs:7557
+    #[arg(long, env = "ACS_URL", global = true)]
+    pub url: Option<String>,
+
+    #[arg(long, env = "ACS_USERNAME", global = true)]
+    pub username: Option<String>,
+
+    #[arg(long, env = "ACS_PASSWORD", global = true, hide_env_values = true)]
+    pub password: Option<String>,
+
+    /// Bearer token, instead of username and password
+    #[arg(long, env = "ACS_TOKEN", global = true, hide_env_values = true)]
+    pub token: Option<String>,
+
+    /// PEM file with the CA certificate of the NBI
+    #[arg(long, env = "ACS_CA_CERT", global = true)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Request timeout in seconds
+    #[arg(long, env = "ACS_TIMEOUT", global = true)]
+    pub timeout: Option<u64>,
+
+    /// Address of the GenieACS file server, e.g. http://genieacs:7567;
+    /// port 7567 of the NBI host by default
+    #[arg(long, env = "ACS_FILE_SERVER", global = true)]
+    pub file_server: Option<String>,
+
+    /// Config file with defaults for the options above
+    #[arg(long, env = "ACS_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+}
+
+impl ConnectionArgs {
+    pub fn connect(&self) -> Result<AcsConnection, Box<dyn std::error::Error>> {
+        let config = Config::load(self.config.as_ref())?;
+
+        let url = self
+            .url
+            .clone()
+            .or(config.url)
+            .ok_or("No ACS address: use --url, ACS_URL or the config file")?;
+        let mut builder = AcsConnection::builder(AcsType::GenieAcs, url);
+
+        if let Some(token) = self.token.as_ref().or(config.token.as_ref()) {
+            builder = builder.bearer_auth(token);
+        } else if let Some(username) = self.username.as_ref().or(config.username.as_ref()) {
+            let password = self.password.as_ref().or(config.password.as_ref());
+            builder = builder.basic_auth(username, password.map(|p| p.as_str()));
+        }
+        if let Some(path) = self.ca_cert.as_ref().or(config.ca_cert.as_ref()) {
+            let pem = std::fs::read(path)
+                .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+            builder = builder.add_root_certificate_pem(&pem);
+        }
+        if let Some(timeout) = self.timeout.or(config.timeout) {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+        if let Some(file_server) = self.file_server.as_ref().or(config.file_server.as_ref()) {
+            builder = builder.file_server(file_server);
+        }
+        return builder.build();
+    }
+}
@@ -0,0 +1,577 @@
+//! `acs`: command-line access to a GenieACS NBI through `AcsConnection`.
+//!
+//! Built with the `cli` feature: `cargo install acs-api-rs --features cli`.
+
+mod config;
+mod output;
+mod shell;
+#[cfg(feature = "tui")]
+mod tui;
+
+use acs_api_rs::config_snapshot::AcsConfigSnapshot;
+use acs_api_rs::connection::AcsConnection;
+use acs_api_rs::file::{AcsFileMetadata, FileType, TransferOptions, TransferReport};
+use acs_api_rs::parameter_value::ParameterValue;
+use acs_api_rs::request::download_command::DownloadCommand;
+use acs_api_rs::util::path::find_outside_selectors;
+use clap::{Parser, Subcommand};
+use config::ConnectionArgs;
+use output::{flatten, print_records, OutputFormat};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "acs",
+    version,
+    about = "Manage TR-069 devices through a GenieACS NBI"
+)]
+struct Cli {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+
+    #[arg(long, short, value_enum, default_value = "table", global = true)]
+    output: OutputFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List devices, optionally matching a GenieACS query
+    Devices {
+        /// MongoDB-style query, e.g. '{"_tags":"lab"}'
+        #[arg(long, short)]
+        query: Option<String>,
+    },
+    /// Get parameter values from the ACS database
+    Get {
+        device: String,
+        #[arg(required = true)]
+        parameters: Vec<String>,
+        /// Refresh the parameters from the device first
+        #[arg(long)]
+        refresh: bool,
+        /// Seconds to wait for the device when refreshing
+        #[arg(long, default_value_t = 30)]
+        wait: u64,
+    },
+    /// Set parameter values, given as NAME=VALUE or NAME:TYPE=VALUE
+    /// (e.g. Device.ManagementServer.PeriodicInformEnable:xsd:boolean=true)
+    Set {
+        device: String,
+        #[arg(required = true)]
+        values: Vec<String>,
+    },
+    /// Refresh an object or parameter from the device
+    Refresh {
+        device: String,
+        object: String,
+        /// Seconds to wait for the device; 0 queues the task and returns
+        #[arg(long, default_value_t = 0)]
+        wait: u64,
+    },
+    /// Reboot a device
+    Reboot { device: String },
+    /// Reset a device to its factory defaults
+    FactoryReset { device: String },
+    /// Add an instance of a multi-instance object, optionally setting
+    /// parameters given relative to the new instance as NAME[:TYPE]=VALUE
+    AddObject {
+        device: String,
+        object: String,
+        values: Vec<String>,
+        /// Seconds to wait for the device
+        #[arg(long, default_value_t = 30)]
+        wait: u64,
+    },
+    /// Delete an instance of a multi-instance object
+    DeleteObject { device: String, object: String },
+    /// Add a tag to a device, or remove it with --remove
+    Tag {
+        device: String,
+        tag: String,
+        #[arg(long)]
+        remove: bool,
+    },
+    /// List files in the ACS file store, optionally matching a query
+    Files {
+        /// e.g. '{"metadata.productClass":"HG8245"}'
+        #[arg(long, short)]
+        query: Option<String>,
+    },
+    /// Upload a file to the ACS
+    Upload {
+        name: String,
+        path: PathBuf,
+        #[arg(long, default_value = "1 Firmware Upgrade Image")]
+        file_type: String,
+        #[arg(long, default_value = "")]
+        oui: String,
+        #[arg(long, default_value = "")]
+        product_class: String,
+        #[arg(long, default_value = "")]
+        version: String,
+        /// Fail and delete the upload unless the file has this MD5
+        #[arg(long)]
+        md5: Option<String>,
+        /// Fail and delete the upload unless the file has this SHA-256
+        #[arg(long)]
+        sha256: Option<String>,
+        /// Keep the upload in the file store if it fails verification
+        #[arg(long)]
+        keep_unverified: bool,
+    },
+    /// Download the content of a file uploaded to the ACS
+    Fetch {
+        name: String,
+        /// Where to write the file, standard output if omitted
+        path: Option<PathBuf>,
+        /// Fail unless the file has this MD5
+        #[arg(long)]
+        md5: Option<String>,
+        /// Fail unless the file has this SHA-256
+        #[arg(long)]
+        sha256: Option<String>,
+    },
+    /// Delete a file from the ACS
+    DeleteFile { name: String },
+    /// Make a device download a file uploaded to the ACS
+    Download {
+        device: String,
+        /// File to download; with --latest, the newest firmware uploaded
+        /// for the device's OUI and product class
+        #[arg(required_unless_present = "latest", conflicts_with = "latest")]
+        file: Option<String>,
+        #[arg(long)]
+        latest: bool,
+        /// File type to send instead of the one from the file's metadata
+        #[arg(long)]
+        file_type: Option<String>,
+        /// Name the device stores the file under
+        #[arg(long)]
+        target_file_name: Option<String>,
+    },
+    /// List pending tasks of a device
+    Tasks { device: String },
+    /// Delete a pending task
+    DeleteTask { task: String },
+    /// List faults of a device
+    Faults { device: String },
+    /// Delete a fault, letting the ACS retry the faulted task
+    DeleteFault { fault: String },
+    /// Export or apply the ACS configuration as a directory of files
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Browse and edit the parameter tree of a device interactively
+    Shell {
+        device: Option<String>,
+        /// Seconds to wait for the device on refresh and add
+        #[arg(long, default_value_t = 30)]
+        wait: u64,
+    },
+    /// Browse devices, parameters, tasks and faults in a terminal UI
+    #[cfg(feature = "tui")]
+    Tui,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Write presets, provisions, virtual parameters, config entries and
+    /// file metadata to a directory
+    Export { dir: PathBuf },
+    /// Make the ACS match a directory written by `export`
+    ///
+    /// Config entries (config.json) are export-only: the NBI cannot write
+    /// them, so differing entries are reported as warnings and left as they
+    /// are, as are files that would need uploading. The command fails when
+    /// such warnings remain unless --allow-warnings is given.
+    Apply {
+        dir: PathBuf,
+        /// Also delete presets, provisions and virtual parameters that are
+        /// not in the directory
+        #[arg(long)]
+        prune: bool,
+        /// Also delete files whose metadata is not in the directory
+        #[arg(long)]
+        prune_files: bool,
+        /// Only print the plan
+        #[arg(long)]
+        dry_run: bool,
+        /// Succeed even if config entries or files still differ
+        #[arg(long)]
+        allow_warnings: bool,
+    },
+}
+
+/// Parses `NAME=VALUE` or `NAME:TYPE=VALUE`; the type defaults to
+/// `xsd:string`. `:` and `=` inside instance selectors of NAME are part of
+/// the name.
+fn parse_parameter_value(arg: &str) -> Result<ParameterValue, Box<dyn std::error::Error>> {
+    let expected = || format!("Expected NAME=VALUE, got {}", arg);
+    let end = find_outside_selectors(arg, &[':', '=']).ok_or_else(expected)?;
+    let (name, rest) = arg.split_at(end);
+    let (value_type, value) = match rest.strip_prefix(':') {
+        Some(rest) => rest.split_once('=').ok_or_else(expected)?,
+        None => ("xsd:string", &rest[1..]),
+    };
+    return Ok(ParameterValue::new(name, value, value_type));
+}
+
+pub(crate) fn parse_parameter_values(
+    args: &[String],
+) -> Result<Vec<ParameterValue>, Box<dyn std::error::Error>> {
+    return args.iter().map(|arg| parse_parameter_value(arg)).collect();
+}
+
+fn print_report(
+    format: OutputFormat,
+    report: &TransferReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    return print_records(
+        format,
+        std::slice::from_ref(report),
+        &["NAME", "LENGTH", "MD5", "SHA256"],
+        |r| {
+            vec![
+                r.name.clone(),
+                r.length.to_string(),
+                r.md5.clone(),
+                r.sha256.clone(),
+            ]
+        },
+    );
+}
+
+fn run(cli: Cli, conn: AcsConnection) -> Result<(), Box<dyn std::error::Error>> {
+    let format = cli.output;
+
+    match cli.command {
+        Command::Devices { query } => {
+            let devices = match query {
+                Some(query) => conn.list_devices_query(&query)?,
+                None => conn.list_devices()?,
+            };
+            print_records(
+                format,
+                &devices,
+                &["ID", "OUI", "PRODUCT CLASS", "SERIAL", "LAST INFORM"],
+                |d| {
+                    vec![
+                        d.id.clone(),
+                        d.device_id.oui.clone(),
+                        d.device_id.product_class.clone(),
+                        d.device_id.serial_number.clone(),
+                        d.last_inform.clone(),
+                    ]
+                },
+            )?;
+        }
+        Command::Get {
+            device,
+            parameters,
+            refresh,
+            wait,
+        } => {
+            let tree = match refresh {
+                true => conn.refresh_parameter_values(
+                    device,
+                    parameters,
+                    Duration::ZERO,
+                    Duration::from_secs(wait),
+                )?,
+                false => conn.get_parameter_values(device, parameters)?,
+            };
+            let mut rows = Vec::new();
+            flatten(&tree, "", &mut rows);
+            print_records(
+                format,
+                &rows,
+                &["PARAMETER", "VALUE", "TYPE", "WRITABLE"],
+                |r| {
+                    vec![
+                        r.parameter.clone(),
+                        r.value.clone(),
+                        r.value_type.clone(),
+                        if r.writable { "yes" } else { "no" }.to_string(),
+                    ]
+                },
+            )?;
+        }
+        Command::Set { device, values } => {
+            conn.set_parameter_values(device, parse_parameter_values(&values)?)?;
+        }
+        Command::Refresh {
+            device,
+            object,
+            wait,
+        } => {
+            if wait == 0 {
+                conn.refresh_object(device, &object)?;
+            } else if !conn.refresh_object_wait(
+                device.clone(),
+                &object,
+                Duration::from_secs(wait),
+            )? {
+                return Err(Box::from(format!(
+                    "Device {} did not complete the refresh within {}s",
+                    device, wait
+                )));
+            }
+        }
+        Command::Reboot { device } => conn.reboot(device)?,
+        Command::FactoryReset { device } => conn.factory_reset(device)?,
+        Command::AddObject {
+            device,
+            object,
+            values,
+            wait,
+        } => {
+            let path = conn.add_object(
+                device,
+                object,
+                parse_parameter_values(&values)?,
+                Duration::from_secs(wait),
+            )?;
+            match format {
+                OutputFormat::Json => println!("{}", serde_json::json!({ "path": path })),
+                OutputFormat::Table => println!("{}", path),
+            }
+        }
+        Command::DeleteObject { device, object } => conn.add_del_object(device, false, object)?,
+        Command::Tag {
+            device,
+            tag,
+            remove,
+        } => conn.add_del_tag(device, !remove, tag)?,
+        Command::Upload {
+            name,
+            path,
+            file_type,
+            oui,
+            product_class,
+            version,
+            md5,
+            sha256,
+            keep_unverified,
+        } => {
+            let metadata = AcsFileMetadata::new(FileType::from(file_type.as_str()))
+                .oui(&oui)
+                .product_class(&product_class)
+                .version(&version);
+            let mut options = TransferOptions::default().keep_unverified(keep_unverified);
+            if let Some(md5) = md5 {
+                options = options.expected_md5(&md5);
+            }
+            if let Some(sha256) = sha256 {
+                options = options.expected_sha256(&sha256);
+            }
+            let report = conn.upload_path(&name, &path, &metadata, &options)?;
+            print_report(format, &report)?;
+        }
+        Command::Fetch {
+            name,
+            path,
+            md5,
+            sha256,
+        } => {
+            let mut options = TransferOptions::default();
+            if let Some(md5) = md5 {
+                options = options.expected_md5(&md5);
+            }
+            if let Some(sha256) = sha256 {
+                options = options.expected_sha256(&sha256);
+            }
+            match path {
+                Some(path) => {
+                    let report = conn.fetch_file_to_path(&name, &path, &options)?;
+                    print_report(format, &report)?;
+                }
+                None => {
+                    conn.fetch_file(&name, &mut std::io::stdout().lock(), &options)?;
+                }
+            }
+        }
+        Command::Files { query } => {
+            let files = match query {
+                Some(query) => conn.list_files_query(&query)?,
+                None => conn.list_files()?,
+            };
+            print_records(
+                format,
+                &files,
+                &[
+                    "NAME",
+                    "TYPE",
+                    "OUI",
+                    "PRODUCT CLASS",
+                    "VERSION",
+                    "LENGTH",
+                    "UPLOADED",
+                ],
+                |f| {
+                    vec![
+                        f.name.clone(),
+                        f.metadata.file_type.to_string(),
+                        f.metadata.oui.clone(),
+                        f.metadata.product_class.clone(),
+                        f.metadata.version.clone(),
+                        f.length.to_string(),
+                        f.upload_date.clone(),
+                    ]
+                },
+            )?;
+        }
+        Command::DeleteFile { name } => conn.delete_file(&name)?,
+        Command::Download {
+            device,
+            file,
+            latest: _,
+            file_type,
+            target_file_name,
+        } => {
+            // Without a file name clap guarantees --latest
+            let file = match file {
+                Some(file) => conn.get_file(&file)?,
+                None => conn.latest_firmware_for_device(&device)?,
+            };
+            let file = file.ok_or("No matching file in the file store")?;
+            let mut command = DownloadCommand::for_file(&file);
+            if let Some(file_type) = file_type {
+                command = command.file_type(FileType::from(file_type.as_str()));
+            }
+            if let Some(target_file_name) = target_file_name {
+                command = command.target_file_name(&target_file_name);
+            }
+            conn.download(device, command)?;
+        }
+        Command::Tasks { device } => {
+            let tasks = conn.list_tasks(&device)?;
+            print_records(
+                format,
+                &tasks,
+                &["ID", "NAME", "TIMESTAMP", "DETAIL"],
+                |t| {
+                    let detail = match t.object_name.is_empty() {
+                        true => t
+                            .parameter_values
+                            .iter()
+                            .map(|pv| pv.join("="))
+                            .collect::<Vec<String>>()
+                            .join(", "),
+                        false => t.object_name.clone(),
+                    };
+                    vec![t.id.clone(), t.name.clone(), t.timestamp.clone(), detail]
+                },
+            )?;
+        }
+        Command::DeleteTask { task } => conn.delete_task(&task)?,
+        Command::Faults { device } => {
+            let faults = conn.list_faults(&device)?;
+            print_records(
+                format,
+                &faults,
+                &["ID", "CODE", "RETRIES", "TIMESTAMP", "MESSAGE"],
+                |f| {
+                    vec![
+                        f.id.clone(),
+                        f.code.clone(),
+                        f.retries.to_string(),
+                        f.timestamp.clone(),
+                        f.message.clone(),
+                    ]
+                },
+            )?;
+        }
+        Command::DeleteFault { fault } => conn.delete_fault(&fault)?,
+        Command::Config {
+            action: ConfigAction::Export { dir },
+        } => conn.export_config()?.write_dir(&dir)?,
+        Command::Config {
+            action:
+                ConfigAction::Apply {
+                    dir,
+                    prune,
+                    prune_files,
+                    dry_run,
+                    allow_warnings,
+                },
+        } => {
+            let desired = AcsConfigSnapshot::read_dir(&dir)?;
+            let plan = conn.plan_config(&desired, prune, prune_files)?;
+            print!("{}", plan);
+            if !dry_run {
+                conn.apply_config(&desired, &plan)?;
+            }
+            if !plan.warnings.is_empty() && !allow_warnings {
+                return Err(Box::from(format!(
+                    "{} difference(s) cannot be applied and remain, see the warnings above \
+                     (pass --allow-warnings to accept them)",
+                    plan.warnings.len()
+                )));
+            }
+        }
+        Command::Shell { device, wait } => {
+            shell::Shell::new(conn, Duration::from_secs(wait)).run(device)?
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui => tui::run(conn)?,
+    }
+    return Ok(());
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = cli.connection.connect().and_then(|conn| run(cli, conn));
+    if let Err(err) = result {
+        // Output piped into e.g. `head` was closed early
+        if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            if err.kind() == std::io::ErrorKind::BrokenPipe {
+                return ExitCode::SUCCESS;
+            }
+        }
+        eprintln!("acs: {}", err);
+        return ExitCode::FAILURE;
+    }
+    return ExitCode::SUCCESS;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_parameter_values() {
+        let pv = parse_parameter_value(
+            "Device.ManagementServer.PeriodicInformInterval:xsd:unsignedInt=300",
+        )
+        .unwrap();
+        assert_eq!(
+            pv.parameter,
+            "Device.ManagementServer.PeriodicInformInterval"
+        );
+        assert_eq!(pv.value_type, "xsd:unsignedInt");
+        assert_eq!(pv.value, "300");
+
+        let pv = parse_parameter_value("Device.WiFi.SSID.1.SSID=a=b:c").unwrap();
+        assert_eq!(pv.parameter, "Device.WiFi.SSID.1.SSID");
+        assert_eq!(pv.value_type, "xsd:string");
+        assert_eq!(pv.value, "a=b:c");
+
+        let pv = parse_parameter_value(r#"Device.Hosts.Host.[MACAddress="aa:bb:cc"].HostName=x"#)
+            .unwrap();
+        assert_eq!(
+            pv.parameter,
+            r#"Device.Hosts.Host.[MACAddress="aa:bb:cc"].HostName"#
+        );
+        assert_eq!(pv.value, "x");
+
+        assert!(parse_parameter_value("Device.DeviceInfo.Description").is_err());
+        assert!(parse_parameter_value("Device.X:xsd:string").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Rendering of command results as aligned tables or JSON.
+
+use acs_api_rs::data_node::DataNode;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::Write;
+
+#[derive(ValueEnum, PartialEq, Clone, Copy, Debug)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// A parameter of a device, flattened out of a `DataNode` tree
+#[derive(Serialize, PartialEq, Clone, Debug)]
+pub struct ParameterRow {
+    pub parameter: String,
+    pub value: String,
+    #[serde(rename = "type")]
+    pub value_type: String,
+    pub writable: bool,
+    pub timestamp: String,
+}
+
+/// Lists the parameters below `node` in path order.
+pub fn flatten(node: &DataNode, prefix: &str, rows: &mut Vec<ParameterRow>) {
+    let mut names: Vec<&String> = node.subnodes.keys().collect();
+    names.sort_by(|a, b| match (a.parse::<u32>(), b.parse::<u32>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    });
+    for name in names {
+        let child = &node.subnodes[name];
+        let path = match prefix.is_empty() {
+            true => name.clone(),
+            false => format!("{}.{}", prefix, name),
+        };
+        if !child.value_type.is_empty() {
+            rows.push(ParameterRow {
+                parameter: path.clone(),
+                value: child.value.clone(),
+                value_type: child.value_type.clone(),
+                writable: child.writable,
+                timestamp: child.timestamp.clone(),
+            });
+        }
+        flatten(child, &path, rows);
+    }
+}
+
+/// Prints `records` as a JSON array, or as a table with one row per record.
+pub fn print_records<T: Serialize>(
+    format: OutputFormat,
+    records: &[T],
+    columns: &[&str],
+    row: impl Fn(&T) -> Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Json => {
+            writeln!(
+                std::io::stdout().lock(),
+                "{}",
+                serde_json::to_string_pretty(records)?
+            )?;
+        }
+        OutputFormat::Table => {
+            let rows: Vec<Vec<String>> = records.iter().map(row).collect();
+            print_table(columns, &rows)?;
+        }
+    }
+    return Ok(());
+}
+
+pub fn print_table(columns: &[&str], rows: &[Vec<String>]) -> std::io::Result<()> {
+    let mut widths: Vec<usize> = columns.iter().map(|c| c.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = std::io::stdout().lock();
+    let mut line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        writeln!(out, "{}", padded.join("  ").trim_end())
+    };
+    line(columns.to_vec())?;
+    for row in rows {
+        line(row.iter().map(|c| c.as_str()).collect())?;
+    }
+    return Ok(());
+}
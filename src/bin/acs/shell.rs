@@ -0,0 +1,476 @@
+//! `acs shell`: interactive browsing and editing of a device's parameter
+//! tree, with tab completion from the fetched tree.
+
+use crate::output::{flatten, print_table};
+use crate::parse_parameter_values;
+use acs_api_rs::connection::AcsConnection;
+use acs_api_rs::data_node::{DataNode, DATA_MODEL_ROOTS};
+use acs_api_rs::parameter_value::ParameterValue;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+const COMMANDS: &[&str] = &[
+    "devices", "use", "pwd", "cd", "ls", "get", "set", "refresh", "add", "del", "help", "exit",
+];
+
+const HELP: &str = "\
+devices                      list devices
+use <device>                 select a device and fetch its parameter tree
+pwd                          print the current object
+cd <path>                    change the current object (.. goes up, / to the root)
+ls [path]                    list the children of an object
+get [path]                   print the parameters below a path
+set <parameter> <value>      set a writable parameter, using its current type
+refresh [path]               refresh a subtree from the device and fetch the tree again
+add <object> [NAME=VALUE..]  add an instance, optionally setting its parameters
+del <instance>               delete an instance
+exit                         leave the shell
+
+Paths are relative to the current object unless they start with /, Device.
+or InternetGatewayDevice.";
+
+/// State shared between the shell and its line-editor helper
+#[derive(Default)]
+struct State {
+    device: Option<String>,
+    devices: Vec<String>,
+    tree: DataNode,
+    cwd: Vec<String>,
+}
+
+impl State {
+    /// Resolves `arg` against the current object. `/` separates path
+    /// components that may be `..` or `.`; within a component `.` separates
+    /// names.
+    fn resolve(&self, arg: &str) -> Vec<String> {
+        let absolute = arg.starts_with('/')
+            || arg
+                .split(['.', '/'])
+                .next()
+                .is_some_and(|first| DATA_MODEL_ROOTS.contains(&first));
+        let mut path = match absolute {
+            true => Vec::new(),
+            false => self.cwd.clone(),
+        };
+        for component in arg.split('/') {
+            match component {
+                ".." => {
+                    path.pop();
+                }
+                "." | "" => {}
+                _ => path.extend(
+                    component
+                        .split('.')
+                        .filter(|name| !name.is_empty())
+                        .map(String::from),
+                ),
+            }
+        }
+        return path;
+    }
+
+    fn node(&self, path: &[String]) -> Option<&DataNode> {
+        return self.tree.get_node(&path.join("."));
+    }
+}
+
+/// Child names of `node` in display order: numbers numerically, GenieACS
+/// metadata (`_object`, ...) left out.
+fn child_names(node: &DataNode) -> Vec<&String> {
+    let mut names: Vec<&String> = node
+        .subnodes
+        .keys()
+        .filter(|name| !name.starts_with('_'))
+        .collect();
+    names.sort_by(|a, b| match (a.parse::<u32>(), b.parse::<u32>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    });
+    return names;
+}
+
+fn is_object(node: &DataNode) -> bool {
+    return node.value_type.is_empty();
+}
+
+struct ShellHelper {
+    state: Rc<RefCell<State>>,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+        let word = &line[start..];
+        let pair = |s: String| Pair {
+            display: s.clone(),
+            replacement: s,
+        };
+
+        let command = line.split_whitespace().next().unwrap_or("");
+        if start == 0 {
+            let candidates = COMMANDS
+                .iter()
+                .filter(|c| c.starts_with(word))
+                .map(|c| pair(c.to_string()))
+                .collect();
+            return Ok((start, candidates));
+        }
+
+        let state = self.state.borrow();
+        if command == "use" {
+            let candidates = state
+                .devices
+                .iter()
+                .filter(|d| d.starts_with(word))
+                .map(|d| pair(d.clone()))
+                .collect();
+            return Ok((start, candidates));
+        }
+
+        // Complete the last name of a path from the children of its parent
+        let split = word.rfind(['.', '/']).map(|i| i + 1).unwrap_or(0);
+        let (parent, prefix) = word.split_at(split);
+        let parent_node = match parent.is_empty() {
+            true => state.node(&state.cwd),
+            false => state.node(&state.resolve(parent)),
+        };
+        let parent_node = match (parent.is_empty(), parent_node) {
+            // At the root, offer the data model roots even before a tree
+            // was fetched
+            (true, _) if state.cwd.is_empty() => {
+                let roots = match state.tree.data_model_root() {
+                    Some(root) => vec![root],
+                    None => DATA_MODEL_ROOTS.to_vec(),
+                };
+                let candidates = roots
+                    .into_iter()
+                    .filter(|root| root.starts_with(prefix))
+                    .map(|root| pair(format!("{}.", root)))
+                    .collect();
+                return Ok((start, candidates));
+            }
+            (_, Some(node)) => node,
+            (_, None) => return Ok((start, Vec::new())),
+        };
+        let candidates = child_names(parent_node)
+            .into_iter()
+            .filter(|name| name.starts_with(prefix))
+            .map(|name| {
+                let suffix = match is_object(&parent_node.subnodes[name]) {
+                    true => ".",
+                    false => "",
+                };
+                pair(format!("{}{}{}", parent, name, suffix))
+            })
+            .collect();
+        return Ok((start, candidates));
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+pub struct Shell {
+    conn: AcsConnection,
+    state: Rc<RefCell<State>>,
+    /// How long to wait for the device on refresh and add
+    wait: Duration,
+}
+
+impl Shell {
+    pub fn new(conn: AcsConnection, wait: Duration) -> Self {
+        return Shell {
+            conn,
+            state: Rc::new(RefCell::new(State::default())),
+            wait,
+        };
+    }
+
+    fn device(&self) -> Result<String, Box<dyn std::error::Error>> {
+        return self
+            .state
+            .borrow()
+            .device
+            .clone()
+            .ok_or_else(|| Box::from("No device selected, see `use`"));
+    }
+
+    fn fetch(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let device = self.device()?;
+        let roots = DATA_MODEL_ROOTS.iter().map(|r| r.to_string()).collect();
+        let tree = self.conn.get_parameter_values(device, roots)?;
+        let mut state = self.state.borrow_mut();
+        state.tree = tree;
+        // Stay as deep as the current object still exists
+        while !state.cwd.is_empty() && state.node(&state.cwd).is_none() {
+            state.cwd.pop();
+        }
+        return Ok(());
+    }
+
+    fn select(&self, device: &str) -> Result<(), Box<dyn std::error::Error>> {
+        {
+            let mut state = self.state.borrow_mut();
+            state.device = Some(device.to_string());
+            state.cwd.clear();
+        }
+        self.fetch()?;
+        let mut state = self.state.borrow_mut();
+        let root = match state.tree.data_model_root() {
+            Some(root) => root.to_string(),
+            None => {
+                return Err(Box::from(format!(
+                    "Device {} has no Device or InternetGatewayDevice tree",
+                    device
+                )))
+            }
+        };
+        state.cwd = vec![root];
+        return Ok(());
+    }
+
+    fn prompt(&self) -> String {
+        let state = self.state.borrow();
+        return match &state.device {
+            Some(device) => format!("{}:{}> ", device, state.cwd.join(".")),
+            None => "acs> ".to_string(),
+        };
+    }
+
+    /// Resolves `arg` to a path of an existing node.
+    fn existing(&self, arg: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let state = self.state.borrow();
+        let path = state.resolve(arg);
+        if state.node(&path).is_none() {
+            return Err(Box::from(format!(
+                "{}: no such object or parameter",
+                path.join(".")
+            )));
+        }
+        return Ok(path);
+    }
+
+    fn execute(&self, line: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return Ok(true),
+        };
+        let args: Vec<&str> = words.collect();
+        let arg = args.first().copied().unwrap_or("");
+
+        match command {
+            "exit" | "quit" => return Ok(false),
+            "help" => println!("{}", HELP),
+            "devices" => {
+                let devices = self.conn.list_devices()?;
+                let rows: Vec<Vec<String>> = devices
+                    .iter()
+                    .map(|d| vec![d.id.clone(), d.last_inform.clone()])
+                    .collect();
+                print_table(&["ID", "LAST INFORM"], &rows)?;
+                self.state.borrow_mut().devices = devices.into_iter().map(|d| d.id).collect();
+            }
+            "use" if !arg.is_empty() => self.select(arg)?,
+            "pwd" => println!("{}", self.state.borrow().cwd.join(".")),
+            "cd" => {
+                let path = self.existing(if arg.is_empty() { "/" } else { arg })?;
+                let mut state = self.state.borrow_mut();
+                if !path.is_empty() && !is_object(state.node(&path).unwrap()) {
+                    return Err(Box::from(format!("{}: not an object", path.join("."))));
+                }
+                state.cwd = path;
+            }
+            "ls" => {
+                self.device()?;
+                let path = self.existing(arg)?;
+                let state = self.state.borrow();
+                let node = state.node(&path).unwrap();
+                let rows: Vec<Vec<String>> = child_names(node)
+                    .into_iter()
+                    .map(|name| {
+                        let child = &node.subnodes[name];
+                        match is_object(child) {
+                            true => vec![
+                                format!("{}.", name),
+                                "".to_string(),
+                                "".to_string(),
+                                "".to_string(),
+                            ],
+                            false => vec![
+                                name.clone(),
+                                child.value.clone(),
+                                child.value_type.clone(),
+                                if child.writable { "rw" } else { "ro" }.to_string(),
+                            ],
+                        }
+                    })
+                    .collect();
+                print_table(&["NAME", "VALUE", "TYPE", "ACCESS"], &rows)?;
+            }
+            "get" => {
+                self.device()?;
+                let path = self.existing(arg)?;
+                let state = self.state.borrow();
+                let node = state.node(&path).unwrap();
+                let mut rows = Vec::new();
+                match is_object(node) {
+                    true => flatten(node, &path.join("."), &mut rows),
+                    false => {
+                        let mut parent = DataNode::new();
+                        parent.subnodes.insert(path.join("."), node.clone());
+                        flatten(&parent, "", &mut rows);
+                    }
+                }
+                let rows: Vec<Vec<String>> = rows
+                    .into_iter()
+                    .map(|r| vec![r.parameter, r.value, r.value_type])
+                    .collect();
+                print_table(&["PARAMETER", "VALUE", "TYPE"], &rows)?;
+            }
+            "set" if args.len() >= 2 => {
+                let device = self.device()?;
+                let path = self.existing(arg)?;
+                let name = path.join(".");
+                // The value is the rest of the line, spaces included
+                let value = line.trim_start()[command.len()..].trim_start()[arg.len()..].trim();
+                let value_type = {
+                    let state = self.state.borrow();
+                    let node = state.node(&path).unwrap();
+                    if is_object(node) {
+                        return Err(Box::from(format!("{}: not a parameter", name)));
+                    }
+                    if !node.writable {
+                        return Err(Box::from(format!("{}: parameter is read-only", name)));
+                    }
+                    node.value_type.clone()
+                };
+                self.conn.set_parameter_values(
+                    device,
+                    vec![ParameterValue::new(&name, value, &value_type)],
+                )?;
+                self.fetch()?;
+            }
+            "refresh" => {
+                let device = self.device()?;
+                let path = self.state.borrow().resolve(arg).join(".");
+                if !self
+                    .conn
+                    .refresh_object_wait(device.clone(), &path, self.wait)?
+                {
+                    println!("Device {} did not respond, the refresh is queued", device);
+                }
+                self.fetch()?;
+            }
+            "add" if !arg.is_empty() => {
+                let device = self.device()?;
+                let path = self.existing(arg)?;
+                let values: Vec<String> = args[1..].iter().map(|a| a.to_string()).collect();
+                let instance = self.conn.add_object(
+                    device,
+                    path.join("."),
+                    parse_parameter_values(&values)?,
+                    self.wait,
+                )?;
+                println!("{}", instance);
+                self.fetch()?;
+            }
+            "del" if !arg.is_empty() => {
+                let device = self.device()?;
+                let path = self.existing(arg)?;
+                let name = path.join(".");
+                {
+                    let state = self.state.borrow();
+                    let node = state.node(&path).unwrap();
+                    if path
+                        .last()
+                        .map(|n| n.parse::<u32>().is_err())
+                        .unwrap_or(true)
+                    {
+                        return Err(Box::from(format!("{}: not an instance", name)));
+                    }
+                    if !node.writable {
+                        return Err(Box::from(format!("{}: instance cannot be deleted", name)));
+                    }
+                }
+                self.conn.add_del_object(device, false, name)?;
+                self.fetch()?;
+            }
+            "use" | "set" | "add" | "del" => {
+                return Err(Box::from("Missing arguments, see `help`"))
+            }
+            _ => {
+                return Err(Box::from(format!(
+                    "Unknown command {}, see `help`",
+                    command
+                )))
+            }
+        }
+        return Ok(true);
+    }
+
+    fn history_path() -> Option<PathBuf> {
+        return Some(PathBuf::from(std::env::var_os("HOME")?).join(".acs_history"));
+    }
+
+    pub fn run(&self, device: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut editor: Editor<ShellHelper, DefaultHistory> = Editor::new()?;
+        editor.set_helper(Some(ShellHelper {
+            state: self.state.clone(),
+        }));
+        let history = Shell::history_path();
+        if let Some(history) = &history {
+            let _ = editor.load_history(history);
+        }
+
+        if let Some(device) = device {
+            self.select(&device)?;
+        }
+
+        loop {
+            let line = match editor.readline(&self.prompt()) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(Box::new(err)),
+            };
+            if !line.trim().is_empty() {
+                let _ = editor.add_history_entry(line.as_str());
+            }
+            match self.execute(&line) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => eprintln!("error: {}", err),
+            }
+        }
+
+        if let Some(history) = &history {
+            let _ = editor.save_history(history);
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,876 @@
+//! `acs tui`: terminal UI with a filterable device list, the parameter tree
+//! of the selected device and its pending tasks and faults.
+
+use acs_api_rs::connection::AcsConnection;
+use acs_api_rs::data_node::{DataNode, DATA_MODEL_ROOTS};
+use acs_api_rs::device::{AcsDevice, AcsFault, AcsTask};
+use acs_api_rs::parameter_value::ParameterValue;
+use acs_api_rs::redact::Redactor;
+use acs_api_rs::util::timestamp::parse_timestamp;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Devices that informed within this age are shown green, within
+/// `STALE_AGE` yellow and after that red.
+const FRESH_AGE: Duration = Duration::from_secs(5 * 60);
+const STALE_AGE: Duration = Duration::from_secs(60 * 60);
+
+const HELP: &str = "q quit  tab pane  / filter  enter open  r refresh  b reboot  s set  R reload";
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Pane {
+    Devices,
+    Tree,
+    Tasks,
+    Faults,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+enum Input {
+    None,
+    Filter,
+    /// Editing the value of a parameter
+    Set {
+        path: String,
+        value_type: String,
+        value: String,
+    },
+    /// Waiting for y/n before rebooting a device
+    ConfirmReboot(String),
+}
+
+/// Parameter tree, tasks and faults of a device
+struct DeviceData {
+    tree: DataNode,
+    tasks: Vec<AcsTask>,
+    faults: Vec<AcsFault>,
+}
+
+impl DeviceData {
+    fn load(conn: &AcsConnection, device: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let roots = DATA_MODEL_ROOTS.iter().map(|r| r.to_string()).collect();
+        return Ok(DeviceData {
+            tree: conn.get_parameter_values(device.to_string(), roots)?,
+            tasks: conn.list_tasks(device)?,
+            faults: conn.list_faults(device)?,
+        });
+    }
+}
+
+/// Data loaded by a worker thread
+enum JobData {
+    /// The open device, reloaded after an action
+    Device(DeviceData),
+    /// A device that was just opened
+    Opened(DeviceData),
+    /// The device list, and the open device if there is one
+    Devices(Vec<AcsDevice>, Option<DeviceData>),
+}
+
+/// Outcome of a worker thread job: its status message and the data it
+/// loaded. Errors are sent as text since boxed errors are not `Send`.
+type JobResult = Result<(String, JobData), String>;
+
+/// A visible line of the parameter tree
+#[derive(Clone, Debug)]
+struct TreeRow {
+    path: String,
+    depth: usize,
+    name: String,
+    node: DataNode,
+}
+
+struct App {
+    conn: AcsConnection,
+    /// Masks values of sensitive parameters, as in the connection's logs
+    redactor: Redactor,
+    devices: Vec<AcsDevice>,
+    filter: String,
+    device_state: ListState,
+    device: Option<String>,
+    tree: DataNode,
+    expanded: HashSet<String>,
+    tree_state: ListState,
+    tasks: Vec<AcsTask>,
+    task_state: ListState,
+    faults: Vec<AcsFault>,
+    fault_state: ListState,
+    pane: Pane,
+    input: Input,
+    status: String,
+    /// Action running on a worker thread, if any
+    job: Option<Receiver<JobResult>>,
+    quit: bool,
+}
+
+fn age(timestamp: &str) -> Option<Duration> {
+    let time = parse_timestamp(timestamp)?;
+    return Some(
+        SystemTime::now()
+            .duration_since(time)
+            .unwrap_or(Duration::ZERO),
+    );
+}
+
+fn format_age(age: Option<Duration>) -> String {
+    let secs = match age {
+        Some(age) => age.as_secs(),
+        None => return "never".to_string(),
+    };
+    return match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    };
+}
+
+fn age_color(age: Option<Duration>) -> Color {
+    return match age {
+        Some(age) if age <= FRESH_AGE => Color::Green,
+        Some(age) if age <= STALE_AGE => Color::Yellow,
+        Some(_) => Color::Red,
+        None => Color::DarkGray,
+    };
+}
+
+/// Moves a list selection by `delta`, staying within `len` items.
+fn step(state: &mut ListState, len: usize, delta: isize) {
+    if len == 0 {
+        state.select(None);
+        return;
+    }
+    let current = state.selected().unwrap_or(0) as isize;
+    state.select(Some((current + delta).clamp(0, len as isize - 1) as usize));
+}
+
+fn visible_rows(
+    node: &DataNode,
+    prefix: &str,
+    depth: usize,
+    expanded: &HashSet<String>,
+    rows: &mut Vec<TreeRow>,
+) {
+    let mut names: Vec<&String> = node
+        .subnodes
+        .keys()
+        .filter(|name| !name.starts_with('_'))
+        .collect();
+    names.sort_by(|a, b| match (a.parse::<u32>(), b.parse::<u32>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    });
+    for name in names {
+        let child = &node.subnodes[name];
+        let path = match prefix.is_empty() {
+            true => name.clone(),
+            false => format!("{}.{}", prefix, name),
+        };
+        rows.push(TreeRow {
+            path: path.clone(),
+            depth,
+            name: name.clone(),
+            node: DataNode {
+                subnodes: Default::default(),
+                ..child.clone()
+            },
+        });
+        if child.value_type.is_empty() && expanded.contains(&path) {
+            visible_rows(child, &path, depth + 1, expanded, rows);
+        }
+    }
+}
+
+impl App {
+    fn new(conn: AcsConnection) -> Self {
+        return App {
+            redactor: conn.redactor(),
+            conn,
+            devices: Vec::new(),
+            filter: String::new(),
+            device_state: ListState::default(),
+            device: None,
+            tree: DataNode::new(),
+            expanded: HashSet::new(),
+            tree_state: ListState::default(),
+            tasks: Vec::new(),
+            task_state: ListState::default(),
+            faults: Vec::new(),
+            fault_state: ListState::default(),
+            pane: Pane::Devices,
+            input: Input::None,
+            status: HELP.to_string(),
+            job: None,
+            quit: false,
+        };
+    }
+
+    /// Reports the outcome of an action in the status line.
+    fn report(&mut self, result: Result<String, Box<dyn std::error::Error>>) {
+        self.status = match result {
+            Ok(message) => message,
+            Err(err) => format!("Error: {}", err),
+        };
+    }
+
+    /// `value` of the parameter at `path` as displayed, masked if the
+    /// parameter is sensitive.
+    fn display_value(&self, path: &str, value: &str) -> String {
+        return self.redactor.redact_value(path, value).to_string();
+    }
+
+    fn filtered_devices(&self) -> Vec<&AcsDevice> {
+        let filter = self.filter.to_lowercase();
+        return self
+            .devices
+            .iter()
+            .filter(|d| {
+                filter.is_empty()
+                    || d.id.to_lowercase().contains(&filter)
+                    || d.device_id.product_class.to_lowercase().contains(&filter)
+                    || d.device_id.serial_number.to_lowercase().contains(&filter)
+            })
+            .collect();
+    }
+
+    fn tree_rows(&self) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        visible_rows(&self.tree, "", 0, &self.expanded, &mut rows);
+        return rows;
+    }
+
+    fn selected_row(&self) -> Option<TreeRow> {
+        let index = self.tree_state.selected()?;
+        return self.tree_rows().into_iter().nth(index);
+    }
+
+    fn show_devices(&mut self, mut devices: Vec<AcsDevice>) {
+        devices.sort_by(|a, b| a.id.cmp(&b.id));
+        self.devices = devices;
+        let len = self.filtered_devices().len();
+        step(&mut self.device_state, len, 0);
+    }
+
+    fn show_device(&mut self, data: DeviceData) {
+        self.tree = data.tree;
+        self.tasks = data.tasks;
+        self.faults = data.faults;
+        let rows = self.tree_rows().len();
+        step(&mut self.tree_state, rows, 0);
+        step(&mut self.task_state, self.tasks.len(), 0);
+        step(&mut self.fault_state, self.faults.len(), 0);
+    }
+
+    /// Runs `job` on a worker thread, so that the UI keeps drawing while the
+    /// ACS or the CPE is contacted. Only one job runs at a time.
+    fn start_job<F>(&mut self, working: String, job: F) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnOnce(&AcsConnection) -> Result<(String, JobData), Box<dyn std::error::Error>>
+            + Send
+            + 'static,
+    {
+        if self.job.is_some() {
+            return Err(Box::from("Another action is still running"));
+        }
+        let conn = self.conn.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(job(&conn).map_err(|err| err.to_string()));
+        });
+        self.job = Some(receiver);
+        self.status = working;
+        return Ok(());
+    }
+
+    /// Runs `action` against the open device on a worker thread and reloads
+    /// the device afterwards.
+    fn spawn<F>(&mut self, working: String, action: F) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnOnce(&AcsConnection, &str) -> Result<String, Box<dyn std::error::Error>>
+            + Send
+            + 'static,
+    {
+        let device = self.device.clone().ok_or("No device open")?;
+        return self.start_job(working, move |conn| {
+            let message = action(conn, &device)?;
+            return Ok((message, JobData::Device(DeviceData::load(conn, &device)?)));
+        });
+    }
+
+    /// Reloads the device list, and the open device if there is one.
+    fn reload(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let device = self.device.clone();
+        return self.start_job("Loading devices…".to_string(), move |conn| {
+            let devices = conn.list_devices()?;
+            let mut message = format!("{} devices", devices.len());
+            let data = match device {
+                Some(device) => {
+                    let data = DeviceData::load(conn, &device)?;
+                    message = format!(
+                        "{}; {}: {} tasks, {} faults",
+                        message,
+                        device,
+                        data.tasks.len(),
+                        data.faults.len()
+                    );
+                    Some(data)
+                }
+                None => None,
+            };
+            return Ok((message, JobData::Devices(devices, data)));
+        });
+    }
+
+    /// Picks up the outcome of a finished worker thread action.
+    fn poll_job(&mut self) {
+        let result = match self.job.as_ref().map(|job| job.try_recv()) {
+            None | Some(Err(TryRecvError::Empty)) => return,
+            Some(Ok(result)) => result,
+            Some(Err(TryRecvError::Disconnected)) => Err("Worker thread failed".to_string()),
+        };
+        self.job = None;
+        self.status = match result {
+            Ok((message, data)) => {
+                match data {
+                    JobData::Device(data) => self.show_device(data),
+                    JobData::Opened(data) => {
+                        if let Some(root) = data.tree.data_model_root() {
+                            self.expanded.insert(root.to_string());
+                        }
+                        self.tree_state.select(Some(0));
+                        self.show_device(data);
+                    }
+                    JobData::Devices(devices, data) => {
+                        self.show_devices(devices);
+                        if let Some(data) = data {
+                            self.show_device(data);
+                        }
+                    }
+                }
+                message
+            }
+            Err(err) => format!("Error: {}", err),
+        };
+    }
+
+    fn open_selected_device(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let index = self.device_state.selected().ok_or("No device selected")?;
+        let id = self
+            .filtered_devices()
+            .get(index)
+            .map(|d| d.id.clone())
+            .ok_or("No device selected")?;
+        let device = id.clone();
+        self.start_job(format!("Opening {}…", id), move |conn| {
+            let data = DeviceData::load(conn, &device)?;
+            let message = format!(
+                "{}: {} tasks, {} faults",
+                device,
+                data.tasks.len(),
+                data.faults.len()
+            );
+            return Ok((message, JobData::Opened(data)));
+        })?;
+        self.device = Some(id);
+        self.show_device(DeviceData {
+            tree: DataNode::new(),
+            tasks: Vec::new(),
+            faults: Vec::new(),
+        });
+        self.pane = Pane::Tree;
+        return Ok(());
+    }
+
+    /// Refreshes the selected object (or the whole tree) from the device.
+    fn refresh(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let object = match (self.pane, self.selected_row()) {
+            (Pane::Tree, Some(row)) => row.path,
+            _ => self.tree.data_model_root().unwrap_or("Device").to_string(),
+        };
+        return self.spawn(format!("Refreshing {}…", object), move |conn, device| {
+            conn.refresh_object(device.to_string(), &object)?;
+            return Ok(format!("Refreshed {}", object));
+        });
+    }
+
+    fn set(
+        &mut self,
+        path: String,
+        value: String,
+        value_type: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let shown = self.display_value(&path, &value);
+        return self.spawn(format!("Setting {}…", path), move |conn, device| {
+            let values = vec![ParameterValue::new(&path, &value, &value_type)];
+            conn.set_parameter_values(device.to_string(), values)?;
+            return Ok(format!("Set {} = {}", path, shown));
+        });
+    }
+
+    fn reboot(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        return self.spawn("Rebooting…".to_string(), |conn, device| {
+            conn.reboot(device.to_string())?;
+            return Ok(format!("Rebooting {}", device));
+        });
+    }
+
+    fn handle_input_key(&mut self, key: KeyEvent) {
+        match (&mut self.input, key.code) {
+            (Input::Filter, KeyCode::Esc) => {
+                self.filter.clear();
+                self.input = Input::None;
+            }
+            (Input::Filter, KeyCode::Enter) => self.input = Input::None,
+            (Input::Filter, KeyCode::Backspace) => {
+                self.filter.pop();
+            }
+            (Input::Filter, KeyCode::Char(c)) => self.filter.push(c),
+            (Input::Set { .. }, KeyCode::Esc) => self.input = Input::None,
+            (Input::Set { value, .. }, KeyCode::Backspace) => {
+                value.pop();
+            }
+            (Input::Set { value, .. }, KeyCode::Char(c)) => value.push(c),
+            (Input::Set { .. }, KeyCode::Enter) => {
+                if let Input::Set {
+                    path,
+                    value_type,
+                    value,
+                } = std::mem::replace(&mut self.input, Input::None)
+                {
+                    if let Err(err) = self.set(path, value, value_type) {
+                        self.report(Err(err));
+                    }
+                }
+            }
+            (Input::ConfirmReboot(_), KeyCode::Char('y')) => {
+                self.input = Input::None;
+                if let Err(err) = self.reboot() {
+                    self.report(Err(err));
+                }
+            }
+            (Input::ConfirmReboot(_), _) => {
+                self.input = Input::None;
+                self.status = "Reboot cancelled".to_string();
+            }
+            _ => {}
+        }
+        if self.input == Input::Filter {
+            let len = self.filtered_devices().len();
+            step(&mut self.device_state, len, 0);
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if self.input != Input::None {
+            self.handle_input_key(key);
+            return;
+        }
+
+        let len = match self.pane {
+            Pane::Devices => self.filtered_devices().len(),
+            Pane::Tree => self.tree_rows().len(),
+            Pane::Tasks => self.tasks.len(),
+            Pane::Faults => self.faults.len(),
+        };
+        let state = match self.pane {
+            Pane::Devices => &mut self.device_state,
+            Pane::Tree => &mut self.tree_state,
+            Pane::Tasks => &mut self.task_state,
+            Pane::Faults => &mut self.fault_state,
+        };
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => step(state, len, -1),
+            KeyCode::Down | KeyCode::Char('j') => step(state, len, 1),
+            KeyCode::PageUp => step(state, len, -10),
+            KeyCode::PageDown => step(state, len, 10),
+            KeyCode::Tab => {
+                self.pane = match self.pane {
+                    Pane::Devices => Pane::Tree,
+                    Pane::Tree => Pane::Tasks,
+                    Pane::Tasks => Pane::Faults,
+                    Pane::Faults => Pane::Devices,
+                }
+            }
+            KeyCode::Char('/') => {
+                self.pane = Pane::Devices;
+                self.input = Input::Filter;
+            }
+            KeyCode::Char('R') => {
+                if let Err(err) = self.reload() {
+                    self.report(Err(err));
+                }
+            }
+            KeyCode::Enter if self.pane == Pane::Devices => {
+                if let Err(err) = self.open_selected_device() {
+                    self.report(Err(err));
+                }
+            }
+            KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Right | KeyCode::Left
+                if self.pane == Pane::Tree =>
+            {
+                if let Some(row) = self.selected_row() {
+                    let expand = match key.code {
+                        KeyCode::Right => true,
+                        KeyCode::Left => false,
+                        _ => !self.expanded.contains(&row.path),
+                    };
+                    if row.node.value_type.is_empty() && expand {
+                        self.expanded.insert(row.path);
+                    } else {
+                        self.expanded.remove(&row.path);
+                    }
+                }
+            }
+            KeyCode::Char('r') => {
+                if let Err(err) = self.refresh() {
+                    self.report(Err(err));
+                }
+            }
+            KeyCode::Char('b') => match &self.device {
+                Some(device) => {
+                    self.status = format!("Reboot {}? (y/n)", device);
+                    self.input = Input::ConfirmReboot(device.clone());
+                }
+                None => self.status = "No device open".to_string(),
+            },
+            KeyCode::Char('s') if self.pane == Pane::Tree => match self.selected_row() {
+                Some(row) if row.node.value_type.is_empty() => {
+                    self.status = format!("{} is an object", row.path)
+                }
+                Some(row) if !row.node.writable => {
+                    self.status = format!("{} is read-only", row.path)
+                }
+                Some(row) => {
+                    self.input = Input::Set {
+                        path: row.path,
+                        value_type: row.node.value_type,
+                        value: row.node.value,
+                    }
+                }
+                None => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn block(&self, title: String, pane: Pane) -> Block<'static> {
+        let style = match self.pane == pane {
+            true => Style::default().fg(Color::Cyan),
+            false => Style::default(),
+        };
+        return Block::default()
+            .borders(Borders::ALL)
+            .border_style(style)
+            .title(title);
+    }
+
+    fn draw_devices(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .filtered_devices()
+            .iter()
+            .map(|d| {
+                let age = age(&d.last_inform);
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:>6} ", format_age(age)),
+                        Style::default().fg(age_color(age)),
+                    ),
+                    Span::raw(d.id.clone()),
+                ]))
+            })
+            .collect();
+        let title = match (&self.input, self.filter.is_empty()) {
+            (Input::Filter, _) => format!("Devices /{}_", self.filter),
+            (_, false) => format!("Devices /{}", self.filter),
+            (_, true) => "Devices".to_string(),
+        };
+        let list = List::new(items)
+            .block(self.block(title, Pane::Devices))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.device_state);
+    }
+
+    fn draw_tree(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .tree_rows()
+            .into_iter()
+            .map(|row| {
+                let indent = "  ".repeat(row.depth);
+                let line = match row.node.value_type.is_empty() {
+                    true => {
+                        let marker = match self.expanded.contains(&row.path) {
+                            true => "▾",
+                            false => "▸",
+                        };
+                        Line::from(format!("{}{} {}", indent, marker, row.name))
+                    }
+                    false => {
+                        let value_style = match row.node.writable {
+                            true => Style::default().fg(Color::White),
+                            false => Style::default().fg(Color::DarkGray),
+                        };
+                        Line::from(vec![
+                            Span::raw(format!("{}  {} = ", indent, row.name)),
+                            Span::styled(
+                                self.display_value(&row.path, &row.node.value),
+                                value_style,
+                            ),
+                            Span::styled(
+                                format!("  {}", row.node.value_type),
+                                Style::default().fg(Color::DarkGray),
+                            ),
+                        ])
+                    }
+                };
+                ListItem::new(line)
+            })
+            .collect();
+        let title = match &self.device {
+            Some(device) => format!("Parameters of {}", device),
+            None => "Parameters".to_string(),
+        };
+        let list = List::new(items)
+            .block(self.block(title, Pane::Tree))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.tree_state);
+    }
+
+    fn draw_tasks(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .tasks
+            .iter()
+            .map(|t| {
+                let detail = match t.object_name.is_empty() {
+                    true => t
+                        .parameter_values
+                        .iter()
+                        .map(|pv| match pv.as_slice() {
+                            [path, value, ..] => {
+                                format!("{}={}", path, self.display_value(path, value))
+                            }
+                            _ => pv.join("="),
+                        })
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                    false => t.object_name.clone(),
+                };
+                ListItem::new(format!("{} {} {}", t.timestamp, t.name, detail))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(self.block(format!("Tasks ({})", self.tasks.len()), Pane::Tasks))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.task_state);
+    }
+
+    fn draw_faults(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .faults
+            .iter()
+            .map(|f| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(f.code.clone(), Style::default().fg(Color::Red)),
+                    Span::raw(format!(" {} (retries {})", f.message, f.retries)),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(self.block(format!("Faults ({})", self.faults.len()), Pane::Faults))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.fault_state);
+    }
+
+    /// The input being edited, or else the status message. Values of
+    /// sensitive parameters are shown as one `*` per character.
+    fn status_line(&self) -> String {
+        return match &self.input {
+            Input::Set { path, value, .. } => {
+                let shown = match self.redactor.is_sensitive(path) {
+                    true => "*".repeat(value.chars().count()),
+                    false => value.clone(),
+                };
+                format!("{} = {}_  (enter to set, esc to cancel)", path, shown)
+            }
+            _ => self.status.clone(),
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)])
+            .areas(frame.area());
+        let [devices, right] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .areas(main);
+        let [tree, bottom] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+            .areas(right);
+        let [tasks, faults] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .areas(bottom);
+
+        self.draw_devices(frame, devices);
+        self.draw_tree(frame, tree);
+        self.draw_tasks(frame, tasks);
+        self.draw_faults(frame, faults);
+
+        frame.render_widget(
+            Paragraph::new(self.status_line())
+                .style(Style::default().add_modifier(Modifier::REVERSED)),
+            status,
+        );
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Box<dyn std::error::Error>> {
+        if let Err(err) = self.reload() {
+            self.report(Err(err));
+        }
+
+        while !self.quit {
+            self.poll_job();
+            terminal.draw(|frame| self.draw(frame))?;
+            // Redraw every second so that last-inform ages stay current, and
+            // more often while waiting for a worker thread
+            let timeout = match self.job {
+                Some(_) => Duration::from_millis(100),
+                None => Duration::from_secs(1),
+            };
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.handle_key(key);
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+pub fn run(conn: AcsConnection) -> Result<(), Box<dyn std::error::Error>> {
+    let mut terminal = ratatui::init();
+    let result = App::new(conn).run(&mut terminal);
+    ratatui::restore();
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acs_api_rs::acs_type::AcsType;
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::from(code));
+    }
+
+    /// Polls `app` as its run loop does until the worker thread finished.
+    fn finish_job(app: &mut App) {
+        for _ in 0..500 {
+            app.poll_job();
+            if app.job.is_none() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Job did not finish");
+    }
+
+    #[test]
+    fn runs_one_job_at_a_time() {
+        let mut conn = AcsConnection::new(AcsType::GenieAcs, "http://127.0.0.1:1".to_string());
+        conn.retry_policy.max_attempts = 1;
+        let mut app = App::new(conn);
+
+        press(&mut app, KeyCode::Char('R'));
+        assert_eq!(app.status, "Loading devices…");
+        press(&mut app, KeyCode::Char('R'));
+        assert_eq!(app.status, "Error: Another action is still running");
+
+        finish_job(&mut app);
+        assert!(app.status.starts_with("Error: "), "{}", app.status);
+        assert!(app.devices.is_empty());
+    }
+
+    fn with_password_pattern() -> App {
+        let mut conn = AcsConnection::new(AcsType::GenieAcs, "http://127.0.0.1:1".to_string());
+        conn.redactor = Some(Redactor::new(vec!["*.Password".to_string()]));
+        return App::new(conn);
+    }
+
+    #[test]
+    fn masks_sensitive_values_being_set() {
+        let mut app = with_password_pattern();
+
+        app.input = Input::Set {
+            path: "Device.ManagementServer.Password".to_string(),
+            value_type: "xsd:string".to_string(),
+            value: "hunter2".to_string(),
+        };
+        assert_eq!(
+            app.status_line(),
+            "Device.ManagementServer.Password = *******_  (enter to set, esc to cancel)"
+        );
+
+        app.input = Input::Set {
+            path: "Device.WiFi.SSID.1.SSID".to_string(),
+            value_type: "xsd:string".to_string(),
+            value: "home".to_string(),
+        };
+        assert!(app.status_line().contains("= home_"));
+        assert_eq!(
+            app.display_value("Device.ManagementServer.Password", "hunter2"),
+            "***"
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn loads_devices_on_a_worker_thread() {
+        use acs_api_rs::mock::{MockNbi, MockRule};
+        use serde_json::json;
+        use std::time::Instant;
+
+        let nbi = MockNbi::new();
+        nbi.add_device(json!({
+            "_id": "cpe-1",
+            "Device": {
+                "_object": true,
+                "DeviceInfo": {
+                    "_object": true,
+                    "UpTime": {"_value": 100, "_type": "xsd:unsignedInt"},
+                },
+            },
+        }))
+        .unwrap();
+        nbi.add_rule(MockRule::delay("/devices", Duration::from_millis(300)).times(1));
+        let mut app = App::new(nbi.connection());
+
+        let started = Instant::now();
+        press(&mut app, KeyCode::Char('R'));
+        assert!(started.elapsed() < Duration::from_millis(200));
+        assert_eq!(app.status, "Loading devices…");
+        finish_job(&mut app);
+        assert_eq!(app.status, "1 devices");
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.status, "Opening cpe-1…");
+        finish_job(&mut app);
+        assert_eq!(app.device.as_deref(), Some("cpe-1"));
+        let rows: Vec<String> = app.tree_rows().into_iter().map(|r| r.path).collect();
+        assert_eq!(rows, vec!["Device", "Device.DeviceInfo"]);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::connection::{conn_event, AcsConnection};
+use crate::parameter_value::ParameterValue;
+use crate::request::download_command::DownloadCommand;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{instrument, Level, Span};
+
+/// Devices a bulk operation applies to.
+#[derive(PartialEq, Clone, Debug)]
+pub enum BulkTarget {
+    /// Explicit list of device ids
+    Devices(Vec<String>),
+    /// GenieACS device query, e.g. `{"_tags":"lab"}`
+    Query(String),
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct BulkOptions {
+    /// Maximum number of devices processed at the same time
+    pub concurrency: usize,
+    /// Minimum interval between starting two consecutive devices
+    pub min_interval: Option<Duration>,
+}
+
+impl Default for BulkOptions {
+    fn default() -> Self {
+        return BulkOptions {
+            concurrency: 4,
+            min_interval: None,
+        };
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct BulkResult {
+    pub device_id: String,
+    /// `None` on success, error message otherwise
+    pub error: Option<String>,
+}
+
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct BulkReport {
+    /// One entry per target device, in target order
+    pub results: Vec<BulkResult>,
+}
+
+impl BulkReport {
+    pub fn succeeded(&self) -> Vec<&BulkResult> {
+        return self.results.iter().filter(|r| r.error.is_none()).collect();
+    }
+
+    pub fn failed(&self) -> Vec<&BulkResult> {
+        return self.results.iter().filter(|r| r.error.is_some()).collect();
+    }
+
+    pub fn is_success(&self) -> bool {
+        return self.results.iter().all(|r| r.error.is_none());
+    }
+}
+
+impl AcsConnection {
+    fn bulk_device_ids(
+        &self,
+        target: &BulkTarget,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        return match target {
+            BulkTarget::Devices(devices) => Ok(devices.clone()),
+            BulkTarget::Query(query) => Ok(self
+                .list_devices_query(query)?
+                .into_iter()
+                .map(|d| d.id)
+                .collect()),
+        };
+    }
+
+    /// Runs `op` for every device of `target` with bounded concurrency and
+    /// rate, collecting a per-device report. Fails only if the target query
+    /// itself fails; per-device failures end up in the report.
+    #[instrument(name = "acs.bulk", skip_all)]
+    pub fn bulk<F>(
+        &self,
+        target: &BulkTarget,
+        options: &BulkOptions,
+        op: F,
+    ) -> Result<BulkReport, Box<dyn std::error::Error>>
+    where
+        F: Fn(&AcsConnection, String) -> Result<(), Box<dyn std::error::Error>> + Sync,
+    {
+        let device_ids = self.bulk_device_ids(target)?;
+        let next = AtomicUsize::new(0);
+        let next_start = Mutex::new(Instant::now());
+        let results: Mutex<Vec<Option<BulkResult>>> = Mutex::new(vec![None; device_ids.len()]);
+
+        let workers = options.concurrency.max(1).min(device_ids.len().max(1));
+        let span = Span::current();
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let _enter = span.enter();
+                    let idx = next.fetch_add(1, Ordering::SeqCst);
+                    if idx >= device_ids.len() {
+                        break;
+                    }
+
+                    if let Some(interval) = options.min_interval {
+                        let wait = {
+                            let mut next_start = next_start.lock().unwrap();
+                            let now = Instant::now();
+                            let start = (*next_start).max(now);
+                            *next_start = start + interval;
+                            start - now
+                        };
+                        std::thread::sleep(wait);
+                    }
+
+                    let device_id = device_ids[idx].clone();
+                    let error = op(self, device_id.clone()).err().map(|e| e.to_string());
+                    conn_event!(self, Level::DEBUG, device_id = %device_id, error = ?error, "Bulk operation finished");
+                    results.lock().unwrap()[idx] = Some(BulkResult { device_id, error });
+                });
+            }
+        });
+
+        return Ok(BulkReport {
+            results: results
+                .into_inner()
+                .unwrap()
+                .into_iter()
+                .flatten()
+                .collect(),
+        });
+    }
+
+    pub fn bulk_set_parameter_values(
+        &self,
+        target: &BulkTarget,
+        options: &BulkOptions,
+        parameter_values: Vec<ParameterValue>,
+    ) -> Result<BulkReport, Box<dyn std::error::Error>> {
+        return self.bulk(target, options, |conn, device_id| {
+            conn.set_parameter_values(device_id, parameter_values.clone())
+        });
+    }
+
+    pub fn bulk_reboot(
+        &self,
+        target: &BulkTarget,
+        options: &BulkOptions,
+    ) -> Result<BulkReport, Box<dyn std::error::Error>> {
+        return self.bulk(target, options, |conn, device_id| conn.reboot(device_id));
+    }
+
+    pub fn bulk_refresh_object(
+        &self,
+        target: &BulkTarget,
+        options: &BulkOptions,
+        object: &str,
+    ) -> Result<BulkReport, Box<dyn std::error::Error>> {
+        return self.bulk(target, options, |conn, device_id| {
+            conn.refresh_object(device_id, object)
+        });
+    }
+
+    /// Sends the same download task, e.g. a file name or a
+    /// `DownloadCommand` with its file type and target name, to every device.
+    pub fn bulk_download(
+        &self,
+        target: &BulkTarget,
+        options: &BulkOptions,
+        command: impl Into<DownloadCommand>,
+    ) -> Result<BulkReport, Box<dyn std::error::Error>> {
+        let command = command.into();
+        return self.bulk(target, options, |conn, device_id| {
+            conn.download(device_id, command.clone())
+        });
+    }
+
+    pub fn bulk_add_del_tag(
+        &self,
+        target: &BulkTarget,
+        options: &BulkOptions,
+        add: bool,
+        tag: &str,
+    ) -> Result<BulkReport, Box<dyn std::error::Error>> {
+        return self.bulk(target, options, |conn, device_id| {
+            conn.add_del_tag(device_id, add, tag.to_string())
+        });
+    }
+}
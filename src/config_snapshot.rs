@@ -0,0 +1,461 @@
+//! Export of the ACS configuration (presets, provisions, virtual parameters,
+//! `/config` entries and file metadata) to a directory, and application of
+//! such a directory back to an ACS through a computed plan.
+//!
+//! Directory layout, with names percent-encoded in file names:
+//!
+//! ```text
+//! presets/<name>.json
+//! provisions/<name>.js
+//! virtual_parameters/<name>.js
+//! config.json                  {"<key>": <value>, ...}
+//! files/<name>.json            metadata of files in the file store
+//! ```
+//!
+//! Files cannot be uploaded from their metadata, so the plan only deletes
+//! files, and only when asked to separately from other resources. Other
+//! file differences are reported as warnings, as are differences in
+//! `/config` entries, which the NBI can read but not write.
+
+use crate::connection::{conn_event, AcsConnection};
+use crate::preset::AcsPreset;
+use crate::provision::AcsProvision;
+use crate::util::file_name::{decode_file_name, encode_file_name};
+use crate::virtual_parameter::AcsVirtualParameter;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::{instrument, Level};
+
+const PRESETS_DIR: &str = "presets";
+const PROVISIONS_DIR: &str = "provisions";
+const VIRTUAL_PARAMETERS_DIR: &str = "virtual_parameters";
+const FILES_DIR: &str = "files";
+const CONFIG_FILE: &str = "config.json";
+
+/// The declarative configuration of an ACS.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct AcsConfigSnapshot {
+    pub presets: BTreeMap<String, AcsPreset>,
+    pub provisions: BTreeMap<String, AcsProvision>,
+    pub virtual_parameters: BTreeMap<String, AcsVirtualParameter>,
+    pub config: BTreeMap<String, Value>,
+    /// File metadata documents from `/files`, keyed by file name
+    pub files: BTreeMap<String, Value>,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum ResourceKind {
+    Provision,
+    VirtualParameter,
+    Preset,
+    File,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ChangeKind {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct PlannedChange {
+    pub resource: ResourceKind,
+    pub name: String,
+    pub change: ChangeKind,
+}
+
+/// Changes needed to make an ACS match a snapshot, in the order they are
+/// applied: scripts before the presets referencing them, deletions of
+/// presets before the scripts they referenced.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct ConfigPlan {
+    pub changes: Vec<PlannedChange>,
+    /// Differences the plan cannot resolve, e.g. files missing on the ACS or
+    /// changed config entries
+    pub warnings: Vec<String>,
+}
+
+impl ConfigPlan {
+    pub fn is_empty(&self) -> bool {
+        return self.changes.is_empty();
+    }
+}
+
+impl std::fmt::Display for ConfigPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.changes.is_empty() {
+            writeln!(f, "No changes")?;
+        }
+        for change in &self.changes {
+            let sign = match change.change {
+                ChangeKind::Create => "+",
+                ChangeKind::Update => "~",
+                ChangeKind::Delete => "-",
+            };
+            writeln!(f, "{} {:?} {}", sign, change.resource, change.name)?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "! {}", warning)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Reads `<dir>/*.<extension>` as `(name, contents)` pairs.
+fn read_entries(
+    dir: &Path,
+    extension: &str,
+) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+    if !dir.is_dir() {
+        return Ok(entries);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(extension) {
+            continue;
+        }
+        let name = decode_file_name(&path)?;
+        entries.push((name, std::fs::read_to_string(&path)?));
+    }
+    return Ok(entries);
+}
+
+/// Replaces the contents of `dir` with `entries`.
+fn write_entries<'a>(
+    dir: &Path,
+    extension: &str,
+    entries: impl Iterator<Item = (&'a String, String)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if dir.is_dir() {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some(extension) {
+                std::fs::remove_file(path)?;
+            }
+        }
+    }
+    std::fs::create_dir_all(dir)?;
+    for (name, contents) in entries {
+        std::fs::write(dir.join(encode_file_name(name, extension)), contents)?;
+    }
+    return Ok(());
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, Box<dyn std::error::Error>> {
+    let mut json = serde_json::to_string_pretty(value)?;
+    json.push('\n');
+    return Ok(json);
+}
+
+/// Whether two file documents from `/files` describe the same file. GridFS
+/// bookkeeping (`_id`, `uploadDate`, `length`, `chunkSize`) differs between
+/// uploads of the same content and is ignored; `md5` is compared when both
+/// documents have one.
+fn same_file(a: &Value, b: &Value) -> bool {
+    if a.get("metadata") != b.get("metadata") {
+        return false;
+    }
+    return match (a.get("md5"), b.get("md5")) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    };
+}
+
+/// Compares desired and current entries of one resource kind.
+fn diff<T: PartialEq>(
+    resource: ResourceKind,
+    desired: &BTreeMap<String, T>,
+    current: &BTreeMap<String, T>,
+    prune: bool,
+    upserts: &mut Vec<PlannedChange>,
+    deletes: &mut Vec<PlannedChange>,
+) {
+    diff_by(
+        resource,
+        desired,
+        current,
+        prune,
+        |a, b| a == b,
+        upserts,
+        deletes,
+    );
+}
+
+/// `diff` with entries considered unchanged when `same` holds.
+fn diff_by<T>(
+    resource: ResourceKind,
+    desired: &BTreeMap<String, T>,
+    current: &BTreeMap<String, T>,
+    prune: bool,
+    same: impl Fn(&T, &T) -> bool,
+    upserts: &mut Vec<PlannedChange>,
+    deletes: &mut Vec<PlannedChange>,
+) {
+    for (name, value) in desired {
+        let change = match current.get(name) {
+            None => ChangeKind::Create,
+            Some(existing) if !same(existing, value) => ChangeKind::Update,
+            Some(_) => continue,
+        };
+        upserts.push(PlannedChange {
+            resource,
+            name: name.clone(),
+            change,
+        });
+    }
+    if prune {
+        for name in current.keys().filter(|name| !desired.contains_key(*name)) {
+            deletes.push(PlannedChange {
+                resource,
+                name: name.clone(),
+                change: ChangeKind::Delete,
+            });
+        }
+    }
+}
+
+impl AcsConfigSnapshot {
+    pub fn read_dir(dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut snapshot = AcsConfigSnapshot::default();
+
+        for (name, json) in read_entries(&dir.join(PRESETS_DIR), "json")? {
+            let mut preset: AcsPreset = serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid preset {}: {}", name, e))?;
+            preset.name = name.clone();
+            snapshot.presets.insert(name, preset);
+        }
+        for (name, script) in read_entries(&dir.join(PROVISIONS_DIR), "js")? {
+            let provision = AcsProvision::new(&name, &script);
+            snapshot.provisions.insert(name, provision);
+        }
+        for (name, script) in read_entries(&dir.join(VIRTUAL_PARAMETERS_DIR), "js")? {
+            let virtual_parameter = AcsVirtualParameter::new(&name, &script);
+            snapshot.virtual_parameters.insert(name, virtual_parameter);
+        }
+        for (name, json) in read_entries(&dir.join(FILES_DIR), "json")? {
+            let metadata: Value = serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid file metadata {}: {}", name, e))?;
+            snapshot.files.insert(name, metadata);
+        }
+        let config_path = dir.join(CONFIG_FILE);
+        if config_path.exists() {
+            snapshot.config = serde_json::from_str(&std::fs::read_to_string(&config_path)?)
+                .map_err(|e| format!("Invalid {}: {}", config_path.display(), e))?;
+        }
+
+        return Ok(snapshot);
+    }
+
+    /// Writes the snapshot to `dir`, removing entries no longer present so
+    /// that the directory mirrors the snapshot exactly.
+    pub fn write_dir(&self, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let presets = self
+            .presets
+            .iter()
+            .map(|(name, preset)| {
+                // The name is the file name
+                let mut json = serde_json::to_value(preset)?;
+                if let Some(obj) = json.as_object_mut() {
+                    obj.remove("_id");
+                }
+                Ok((name, to_json(&json)?))
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        write_entries(&dir.join(PRESETS_DIR), "json", presets.into_iter())?;
+        write_entries(
+            &dir.join(PROVISIONS_DIR),
+            "js",
+            self.provisions.iter().map(|(n, p)| (n, p.script.clone())),
+        )?;
+        write_entries(
+            &dir.join(VIRTUAL_PARAMETERS_DIR),
+            "js",
+            self.virtual_parameters
+                .iter()
+                .map(|(n, v)| (n, v.script.clone())),
+        )?;
+        let files = self
+            .files
+            .iter()
+            .map(|(name, metadata)| Ok((name, to_json(metadata)?)))
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        write_entries(&dir.join(FILES_DIR), "json", files.into_iter())?;
+        std::fs::write(dir.join(CONFIG_FILE), to_json(&self.config)?)?;
+        return Ok(());
+    }
+
+    /// Plan turning `current` into this snapshot. Without `prune`, presets,
+    /// provisions and virtual parameters missing from the snapshot are left
+    /// alone; without `prune_files`, so are files.
+    pub fn plan_from(
+        &self,
+        current: &AcsConfigSnapshot,
+        prune: bool,
+        prune_files: bool,
+    ) -> ConfigPlan {
+        let mut upserts = Vec::new();
+        let mut deletes = Vec::new();
+        diff(
+            ResourceKind::Provision,
+            &self.provisions,
+            &current.provisions,
+            prune,
+            &mut upserts,
+            &mut deletes,
+        );
+        diff(
+            ResourceKind::VirtualParameter,
+            &self.virtual_parameters,
+            &current.virtual_parameters,
+            prune,
+            &mut upserts,
+            &mut deletes,
+        );
+        diff(
+            ResourceKind::Preset,
+            &self.presets,
+            &current.presets,
+            prune,
+            &mut upserts,
+            &mut deletes,
+        );
+
+        let mut file_changes = Vec::new();
+        diff_by(
+            ResourceKind::File,
+            &self.files,
+            &current.files,
+            prune_files,
+            same_file,
+            &mut file_changes,
+            &mut deletes,
+        );
+        let mut warnings: Vec<String> = file_changes
+            .iter()
+            .map(|c| match c.change {
+                ChangeKind::Create => format!("File {} is missing on the ACS, upload it", c.name),
+                _ => format!("File {} differs on the ACS, upload it again", c.name),
+            })
+            .collect();
+        // The NBI has no write route for config entries
+        for (key, value) in &self.config {
+            match current.config.get(key) {
+                None => warnings.push(format!(
+                    "Config {} is missing on the ACS, set it in the GenieACS UI",
+                    key
+                )),
+                Some(existing) if existing != value => warnings.push(format!(
+                    "Config {} differs on the ACS, set it in the GenieACS UI",
+                    key
+                )),
+                Some(_) => {}
+            }
+        }
+
+        // Presets first, so that nothing references deleted scripts
+        deletes.sort_by_key(|c| match c.resource {
+            ResourceKind::Preset => 0,
+            _ => 1,
+        });
+        upserts.extend(deletes);
+        return ConfigPlan {
+            changes: upserts,
+            warnings,
+        };
+    }
+}
+
+impl AcsConnection {
+    /// Reads the current configuration of the ACS.
+    #[instrument(name = "acs.export_config", skip_all)]
+    pub fn export_config(&self) -> Result<AcsConfigSnapshot, Box<dyn std::error::Error>> {
+        let files = self
+            .get_collection("files")?
+            .into_iter()
+            .filter_map(|doc| {
+                let name = doc.get("_id")?.as_str()?.to_string();
+                Some((name, doc))
+            })
+            .collect();
+        return Ok(AcsConfigSnapshot {
+            presets: self
+                .list_presets()?
+                .into_iter()
+                .map(|p| (p.name.clone(), p))
+                .collect(),
+            provisions: self
+                .list_provisions()?
+                .into_iter()
+                .map(|p| (p.name.clone(), p))
+                .collect(),
+            virtual_parameters: self
+                .list_virtual_parameters()?
+                .into_iter()
+                .map(|v| (v.name.clone(), v))
+                .collect(),
+            config: self.list_config()?,
+            files,
+        });
+    }
+
+    /// Plan making the ACS match `desired`; applying nothing, this is a dry
+    /// run of `apply_config`.
+    #[instrument(name = "acs.plan_config", skip_all)]
+    pub fn plan_config(
+        &self,
+        desired: &AcsConfigSnapshot,
+        prune: bool,
+        prune_files: bool,
+    ) -> Result<ConfigPlan, Box<dyn std::error::Error>> {
+        let current = self.export_config()?;
+        return Ok(desired.plan_from(&current, prune, prune_files));
+    }
+
+    /// Applies a plan computed by `plan_config` for `desired`. Stops at the
+    /// first failing change.
+    ///
+    /// Only `plan.changes` are applied. `/config` entries are export-only:
+    /// the NBI has no route writing them, so differing entries stay as they
+    /// are on the ACS and are only listed in `plan.warnings`, together with
+    /// file differences. Callers that need the ACS to match `desired` fully
+    /// must check `plan.warnings` is empty.
+    #[instrument(name = "acs.apply_config", skip_all, fields(changes = plan.changes.len()))]
+    pub fn apply_config(
+        &self,
+        desired: &AcsConfigSnapshot,
+        plan: &ConfigPlan,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for change in &plan.changes {
+            conn_event!(self, Level::INFO, resource = ?change.resource, name = %change.name, change = ?change.change, "Applying");
+            let name = change.name.as_str();
+            let missing = || format!("{:?} {} is not in the snapshot", change.resource, name);
+            match (change.resource, change.change) {
+                (ResourceKind::Provision, ChangeKind::Delete) => self.delete_provision(name)?,
+                (ResourceKind::Provision, _) => {
+                    self.put_provision(desired.provisions.get(name).ok_or_else(missing)?)?
+                }
+                (ResourceKind::VirtualParameter, ChangeKind::Delete) => {
+                    self.delete_virtual_parameter(name)?
+                }
+                (ResourceKind::VirtualParameter, _) => self.put_virtual_parameter(
+                    desired.virtual_parameters.get(name).ok_or_else(missing)?,
+                )?,
+                (ResourceKind::Preset, ChangeKind::Delete) => self.delete_preset(name)?,
+                (ResourceKind::Preset, _) => {
+                    self.put_preset(desired.presets.get(name).ok_or_else(missing)?)?
+                }
+                (ResourceKind::File, ChangeKind::Delete) => self.delete_file(name)?,
+                (ResourceKind::File, _) => {
+                    return Err(Box::from(format!(
+                        "File {} must be uploaded, it cannot be applied from metadata",
+                        name
+                    )))
+                }
+            }
+        }
+        return Ok(());
+    }
+}
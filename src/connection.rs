@@ -1,55 +1,419 @@
 use crate::acs_type::*;
+use crate::connection_builder::*;
 use crate::data_node::*;
 use crate::device::*;
+use crate::file::*;
+use crate::middleware::Middleware;
 use crate::parameter_value::*;
+use crate::preset::*;
+use crate::provision::*;
+use crate::redact::*;
 use crate::request::add_delete_object::*;
 use crate::request::download_command::*;
 use crate::request::refresh_object::*;
 use crate::request::set_parameter_values::*;
 use crate::request::simple_command::*;
-use reqwest::blocking::Client;
-use reqwest::header::{HeaderMap, HeaderValue};
+use crate::retry::RetryPolicy;
+use crate::transport::*;
+use crate::util::path::{has_selector, selector_prefix};
+use crate::util::timestamp::parse_timestamp;
+use crate::util::version::compare_versions;
+use crate::virtual_parameter::*;
+use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use reqwest::Method;
 use serde_json::Value;
-use std::collections::HashMap;
-use std::time::Duration;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{field, info_span, instrument, trace, Level, Span};
 use urlencoding::encode;
 
+/// Whether `node` or any of its leaf parameters was last refreshed before
+/// `threshold`. Nodes without a usable timestamp are considered stale.
+fn is_stale(node: &DataNode, threshold: SystemTime) -> bool {
+    let leaves: Vec<&DataNode> = node
+        .subnodes
+        .iter()
+        .filter(|(name, _)| !name.starts_with('_'))
+        .map(|(_, subnode)| subnode)
+        .collect();
+
+    if leaves.is_empty() {
+        return match parse_timestamp(&node.timestamp) {
+            Some(timestamp) => timestamp < threshold,
+            None => true,
+        };
+    }
+
+    return leaves.iter().any(|subnode| is_stale(subnode, threshold));
+}
+
+/// Emits a `tracing` event at `$level` if the `log_level` of connection
+/// `$conn` allows it.
+macro_rules! conn_event {
+    ($conn:expr, $level:expr, $($arg:tt)+) => {
+        if $conn.logs($level) {
+            tracing::event!($level, $($arg)+);
+        }
+    };
+}
+pub(crate) use conn_event;
+
+/// Traces the parameters of `node`, the object at `path`, masking the
+/// values `redactor` considers sensitive.
+fn trace_parameters(redactor: &Redactor, path: &str, node: &DataNode) {
+    for (name, subnode) in &node.subnodes {
+        let path = format!("{}.{}", path, name);
+        if subnode.value_type.is_empty() {
+            trace_parameters(redactor, &path, subnode);
+            continue;
+        }
+        trace!(
+            path = %path,
+            value = %redactor.redact_value(&path, &subnode.value),
+            value_type = %subnode.value_type,
+            writable = subnode.writable,
+            "Parameter"
+        );
+    }
+}
+
+/// Top-level objects of a device document returned by `get_parameter_values`
+const PARAMETER_ROOTS: &[&str] = &["Device", "InternetGatewayDevice", "VirtualParameters"];
+
+/// Port the GenieACS file server listens on unless configured otherwise
+pub(crate) const DEFAULT_FILE_SERVER_PORT: u16 = 7567;
+
+/// Connection to an ACS northbound interface.
+///
+/// `AcsConnection` is `Clone + Send + Sync`, which is checked at compile
+/// time, so it can be cloned into worker threads or shared behind an
+/// `Arc`. Cloning is cheap: all clones share the same HTTP connection pool,
+/// while settings such as `timeout`, `auth`, `retry_policy` and
+/// `log_level` belong to each clone and can be adjusted independently
+/// (`with_timeout`, `with_middleware`, `with_log_level`).
+///
+/// Every operation is instrumented with a `tracing` span named after it
+/// (`acs.list_devices`, `acs.set_parameter_values`, ...) carrying the device
+/// id, with a nested `acs.http` span per HTTP request recording method,
+/// URL, status, latency and byte counts.
+#[derive(Clone)]
 pub struct AcsConnection {
     pub addr: String,
     pub acs_type: AcsType,
-    pub debug_log: bool,
-    pub list_debug_log: bool,
-    pub get_debug_log: bool,
-    pub parse_debug_log: bool,
-    client: Client,
+    /// Total timeout of a single request
+    pub timeout: Duration,
+    /// Credentials sent with every request
+    pub auth: Option<AcsAuth>,
+    /// Retries of transient failures
+    pub retry_policy: RetryPolicy,
+    /// Hooks run around every request, in order
+    pub middleware: Vec<Arc<dyn Middleware>>,
+    /// Address of the GenieACS file server, e.g. http://genieacs:7567;
+    /// without one, the NBI host on port 7567 is assumed
+    pub file_server: Option<String>,
+    /// Patterns of sensitive parameters masked in logs; the process-wide
+    /// patterns of `redact` if `None`
+    pub redactor: Option<Redactor>,
+    /// Credentials for the file server; the NBI credentials are never sent
+    /// there, as it may be a different host
+    pub file_server_auth: Option<AcsAuth>,
+    /// Most verbose level of the events and `acs.http` spans this clone
+    /// emits, on top of the subscriber's filter; all levels if `None`
+    pub log_level: Option<Level>,
+    transport: Arc<dyn HttpTransport>,
 }
 
+// AcsConnection is meant to be shared between threads
+const _: fn() = || {
+    fn assert_clone_send_sync<T: Clone + Send + Sync>() {}
+    assert_clone_send_sync::<AcsConnection>();
+};
+
 impl AcsConnection {
+    /// Creates a connection with default settings. Use `builder` to
+    /// configure authentication, TLS, proxy or timeouts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the HTTP client cannot be initialised, e.g. because the
+    /// system TLS configuration cannot be loaded; `try_new` returns the
+    /// error instead.
     pub fn new(acs_type: AcsType, addr: String) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .connect_timeout(Duration::from_secs(10))
-            .pool_idle_timeout(Duration::from_secs(90))
-            .pool_max_idle_per_host(10)
-            .build()
-            .expect("Failed to build HTTP client");
+        return Self::try_new(acs_type, addr).expect("Failed to build HTTP client");
+    }
+
+    /// Same as `new`, but fails instead of panicking if the HTTP client
+    /// cannot be initialised.
+    pub fn try_new(acs_type: AcsType, addr: String) -> Result<Self, Box<dyn std::error::Error>> {
+        return Self::builder(acs_type, addr).build();
+    }
 
+    pub fn builder(acs_type: AcsType, addr: String) -> AcsConnectionBuilder {
+        return AcsConnectionBuilder::new(acs_type, addr);
+    }
+
+    /// Creates a connection sending its requests through `transport`, e.g.
+    /// a fake one in tests.
+    pub fn with_transport(
+        acs_type: AcsType,
+        addr: String,
+        transport: Arc<dyn HttpTransport>,
+    ) -> Self {
         return Self {
             acs_type,
             addr,
-            debug_log: false,
-            list_debug_log: false,
-            get_debug_log: false,
-            parse_debug_log: false,
-            client,
+            timeout: Duration::from_secs(30),
+            auth: None,
+            retry_policy: RetryPolicy::default(),
+            middleware: Vec::new(),
+            file_server: None,
+            file_server_auth: None,
+            redactor: None,
+            log_level: None,
+            transport,
+        };
+    }
+
+    /// Returns a clone using `timeout` as the total timeout of a request.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut conn = self.clone();
+        conn.timeout = timeout;
+        return conn;
+    }
+
+    /// Returns a clone that additionally runs `middleware` around every
+    /// request.
+    pub fn with_middleware(&self, middleware: Arc<dyn Middleware>) -> Self {
+        let mut conn = self.clone();
+        conn.middleware.push(middleware);
+        return conn;
+    }
+
+    /// Returns a clone emitting events up to `level` only, e.g. `DEBUG`
+    /// for one worker and `WARN` for the others, with a subscriber that
+    /// lets all of them through.
+    pub fn with_log_level(&self, level: Level) -> Self {
+        let mut conn = self.clone();
+        conn.log_level = Some(level);
+        return conn;
+    }
+
+    /// Whether this clone emits events at `level`.
+    pub(crate) fn logs(&self, level: Level) -> bool {
+        return self.log_level.map(|max| level <= max).unwrap_or(true);
+    }
+
+    /// Redactor in effect: this connection's own patterns, or else the
+    /// process-wide ones.
+    pub fn redactor(&self) -> Redactor {
+        return match &self.redactor {
+            Some(redactor) => redactor.clone(),
+            None => Redactor::global(),
+        };
+    }
+
+    fn request(&self, method: Method, url: &str) -> HttpRequest {
+        return self.request_with_auth(method, url, self.auth.as_ref());
+    }
+
+    fn request_with_auth(&self, method: Method, url: &str, auth: Option<&AcsAuth>) -> HttpRequest {
+        let builder = HttpRequest::new(method, url).timeout(self.timeout);
+        return match auth {
+            Some(AcsAuth::Basic { username, password }) => {
+                builder.basic_auth(username, password.as_deref())
+            }
+            Some(AcsAuth::Bearer(token)) => builder.bearer_auth(token),
+            None => builder,
+        };
+    }
+
+    /// Executes a single HTTP request within an `acs.http` span, passing it
+    /// through the middleware chain.
+    fn execute(
+        &self,
+        mut request: HttpRequest,
+        attempt: u32,
+    ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+        for middleware in self.middleware.iter() {
+            middleware.on_request(&mut request)?;
+        }
+
+        let method = request.method.clone();
+        let url = request.url.clone();
+        let request_bytes = match &request.body {
+            HttpBody::Empty => Some(0),
+            HttpBody::Bytes(bytes) => Some(bytes.len() as u64),
+            HttpBody::Reader(_, length) => *length,
+        };
+        let span = match self.logs(Level::INFO) {
+            true => info_span!(
+                "acs.http",
+                method = %method,
+                url = %redact_url(&url),
+                attempt,
+                request_bytes,
+                status = field::Empty,
+                latency_ms = field::Empty,
+                response_bytes = field::Empty,
+            ),
+            false => Span::none(),
         };
+        let _enter = span.enter();
+
+        let started = Instant::now();
+        let result = self.transport.execute(request);
+        let elapsed = started.elapsed();
+        let latency_ms = elapsed.as_millis() as u64;
+        span.record("latency_ms", latency_ms);
+
+        match &result {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                span.record("status", status);
+                if let Some(length) = response.content_length() {
+                    span.record("response_bytes", length);
+                }
+                conn_event!(
+                    self,
+                    Level::DEBUG,
+                    status,
+                    latency_ms,
+                    response_bytes = response.content_length(),
+                    "Response"
+                );
+                for middleware in self.middleware.iter() {
+                    middleware.on_response(&method, &url, response, elapsed);
+                }
+            }
+            Err(err) => {
+                conn_event!(self, Level::DEBUG, error = %err, latency_ms, "Request failed");
+                for middleware in self.middleware.iter() {
+                    middleware.on_error(&method, &url, err, elapsed);
+                }
+            }
+        }
+
+        return Ok(result?);
+    }
+
+    /// Sends a request, retrying transient failures per `retry_policy`.
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+        let idempotent = request.method != Method::POST;
+        let mut attempt: u32 = 1;
+
+        loop {
+            let current = match request.try_clone() {
+                Some(current) => current,
+                // Streaming bodies cannot be replayed
+                None => return self.execute(request, attempt),
+            };
+
+            match self.execute(current, attempt) {
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    if !self
+                        .retry_policy
+                        .should_retry_status(attempt, idempotent, status)
+                    {
+                        return Ok(response);
+                    }
+                    let delay = response
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .map(Duration::from_secs)
+                        .unwrap_or(self.retry_policy.backoff(attempt))
+                        .min(self.retry_policy.max_backoff);
+                    conn_event!(
+                        self,
+                        Level::WARN,
+                        status,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        "Transient failure, retrying"
+                    );
+                    std::thread::sleep(delay);
+                }
+                Err(err) => {
+                    // Only transport errors are retried, not middleware ones
+                    let retry = match err.downcast_ref::<TransportError>() {
+                        Some(http_err) => self
+                            .retry_policy
+                            .should_retry_error(attempt, idempotent, http_err),
+                        None => false,
+                    };
+                    if !retry {
+                        return Err(err);
+                    }
+                    let delay = self.retry_policy.backoff(attempt);
+                    conn_event!(self, Level::WARN,
+                        error = %err,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        "Transient failure, retrying"
+                    );
+                    std::thread::sleep(delay);
+                }
+            }
+
+            attempt += 1;
+        }
     }
 
     fn encode_device(&self, device_id: &str) -> String {
         return encode(device_id).to_string();
     }
 
-    pub fn list_devices(self: &Self) -> Result<Vec<AcsDevice>, Box<dyn std::error::Error>> {
+    /// Replaces instance selectors such as `[MACAddress="aa:bb:cc:dd:ee:ff"]`
+    /// or `[alias]` in `paths` with instance numbers, using the current ACS
+    /// database copy of the device. Paths without selectors are returned
+    /// unchanged and cost no request.
+    #[instrument(name = "acs.resolve_paths", skip_all, fields(device_id = %device_id))]
+    pub fn resolve_paths(
+        &self,
+        device_id: String,
+        paths: Vec<String>,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        if !paths.iter().any(|p| has_selector(p)) {
+            return Ok(paths);
+        }
+
+        let mut prefixes: Vec<String> = Vec::new();
+        for path in paths.iter().filter(|p| has_selector(p)) {
+            let prefix = selector_prefix(path)?;
+            if !prefixes.contains(&prefix) {
+                prefixes.push(prefix);
+            }
+        }
+
+        let tree = self.get_parameter_values(device_id, prefixes)?;
+        return paths
+            .iter()
+            .map(|path| {
+                if has_selector(path) {
+                    tree.resolve_path(path)
+                } else {
+                    Ok(path.clone())
+                }
+            })
+            .collect();
+    }
+
+    /// Single-path variant of `resolve_paths`.
+    #[instrument(name = "acs.resolve_path", skip_all, fields(device_id = %device_id))]
+    pub fn resolve_path(
+        &self,
+        device_id: String,
+        path: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut resolved = self.resolve_paths(device_id, vec![path.to_string()])?;
+        return Ok(resolved.remove(0));
+    }
+
+    #[instrument(name = "acs.list_devices", skip_all)]
+    pub fn list_devices(&self) -> Result<Vec<AcsDevice>, Box<dyn std::error::Error>> {
         if !matches!(self.acs_type, AcsType::GenieAcs) {
             return Err(Box::from("Unknown ACS type"));
         }
@@ -57,24 +421,48 @@ impl AcsConnection {
         // Define the URL
         let url = format!("{}/devices", self.addr);
 
-        if self.list_debug_log {
-            eprintln!("[list_devices] URL: {}", url);
-        }
-
         // Send a GET request
-        let response = self.client.get(&url).send()?;
-
-        if self.list_debug_log {
-            eprintln!("[list_devices] Status: {:?}", response.status());
-        }
+        let response = self.send(self.request(Method::GET, &url))?;
 
         // Check if the request was successful
         if response.status().is_success() {
             // Parse the JSON response
-            let s = response.text()?.clone();
-            if self.list_debug_log {
-                eprintln!("[list_devices] Response body: {}", s);
-            }
+            let s = response.text()?;
+            conn_event!(self, Level::TRACE, body = %self.redactor().body(&s), "Response body");
+            let val: Vec<AcsDevice> = serde_json::from_str(&s)?;
+            return Ok(val);
+        } else {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+    }
+
+    /// Lists devices matching a GenieACS (MongoDB-style) query, e.g.
+    /// `{"_deviceId._ProductClass":"HGW"}` or `{"_tags":"lab"}`.
+    #[instrument(name = "acs.list_devices_query", skip_all, fields(query = %query))]
+    pub fn list_devices_query(
+        &self,
+        query: &str,
+    ) -> Result<Vec<AcsDevice>, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        // Define the URL
+        let url = format!(
+            "{}/devices?query={}&projection=_id,_deviceId,_lastInform,_registered",
+            self.addr,
+            encode(query)
+        );
+
+        // Send a GET request
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if response.status().is_success() {
+            let s = response.text()?;
+            conn_event!(self, Level::TRACE, body = %self.redactor().body(&s), "Response body");
             let val: Vec<AcsDevice> = serde_json::from_str(&s)?;
             return Ok(val);
         } else {
@@ -85,6 +473,7 @@ impl AcsConnection {
         }
     }
 
+    #[instrument(name = "acs.set_parameter_values", skip_all, fields(device_id = %device_id))]
     pub fn set_parameter_values(
         &self,
         device_id: String,
@@ -94,6 +483,18 @@ impl AcsConnection {
             return Err(Box::from("Unknown ACS type"));
         }
 
+        // Replace instance selectors with instance numbers
+        let names = parameter_values
+            .iter()
+            .map(|pv| pv.parameter.clone())
+            .collect();
+        let names = self.resolve_paths(device_id.clone(), names)?;
+        let parameter_values: Vec<ParameterValue> = parameter_values
+            .iter()
+            .zip(names)
+            .map(|(pv, name)| ParameterValue::new(&name, &pv.value, &pv.value_type))
+            .collect();
+
         // Define the URL
         let url = format!(
             "{}/devices/{}/tasks?connection_request",
@@ -101,37 +502,10 @@ impl AcsConnection {
             self.encode_device(&device_id)
         );
 
-        if self.debug_log {
-            eprintln!("[set_parameter_values] URL: {}", url);
-        }
         let req = SetParameterValues::new(parameter_values.clone());
-        if self.debug_log {
-            eprintln!(
-                "[set_parameter_values] Request: {}",
-                serde_json::to_string(&req).unwrap()
-            );
-        }
-
+        conn_event!(self, Level::DEBUG, request = %self.redactor().json_string(&req), "Request");
         // Send a POST request
-        let response = self.client.post(&url).json(&req).send();
-
-        match response {
-            Ok(ref _resp) => {}
-            Err(err) => {
-                if self.debug_log {
-                    eprintln!(
-                        "[set_parameter_values] HTTP error while sending request: {:?}",
-                        err
-                    );
-                }
-                return Err(Box::from(err));
-            }
-        };
-
-        let response = response.unwrap();
-        if self.debug_log {
-            eprintln!("[set_parameter_values] Status: {:?}", response.status());
-        }
+        let response = self.send(self.request(Method::POST, &url).json(&req)?)?;
 
         if response.status().is_success() {
             return Ok(());
@@ -143,62 +517,17 @@ impl AcsConnection {
         }
     }
 
-    fn parse_device_tree(&self, json: &Value) -> DataNode {
-        let mut root = DataNode::new();
-
-        if self.parse_debug_log {
-            eprintln!("[parse_device_tree] Parsing node: {}", json);
-        }
-
-        if let Some(obj) = json.as_object() {
-            for (key, value) in obj {
-                if self.parse_debug_log {
-                    eprintln!("[parse_device_tree] Processing key: {}", key);
-                }
-                let mut child_node = DataNode::new();
-
-                if let Some(sub_obj) = value.as_object() {
-                    if sub_obj.contains_key("_value") && sub_obj.contains_key("_type") {
-                        child_node.value = match &sub_obj["_value"] {
-                            Value::Bool(b) => b.to_string(),
-                            Value::String(s) => s.clone(),
-                            Value::Number(n) => n.to_string(),
-                            _ => "".to_string(),
-                        };
-                        child_node.value_type = sub_obj["_type"]
-                            .as_str()
-                            .map(String::from)
-                            .unwrap_or("".to_string());
-                        if self.parse_debug_log {
-                            eprintln!(
-                                "[parse_device_tree] key={} value={} type={} writable={:?}",
-                                key,
-                                child_node.value,
-                                child_node.value_type,
-                                sub_obj.get("_writable")
-                            );
-                        }
-                        if sub_obj.contains_key("_writable") {
-                            child_node.writable = sub_obj
-                                .get("_writable")
-                                .and_then(|w| w.as_bool())
-                                .unwrap_or(false);
-                        } else {
-                            /* Should it be considered writable? */
-                            child_node.writable = false;
-                        }
-                    } else {
-                        child_node = self.parse_device_tree(value);
-                    }
-                }
-
-                root.subnodes.insert(key.clone(), child_node);
-            }
+    /// Parses the tree under `root` of a device document, tracing every
+    /// parameter by its full path with sensitive values masked.
+    fn parse_device_tree(&self, root: &str, json: &Value) -> DataNode {
+        let node = DataNode::from_genieacs_json(json);
+        if self.logs(Level::TRACE) && tracing::enabled!(Level::TRACE) {
+            trace_parameters(&self.redactor(), root, &node);
         }
-
-        root
+        return node;
     }
 
+    #[instrument(name = "acs.get_parameter_values", skip_all, fields(device_id = %device_id))]
     pub fn get_parameter_values(
         &self,
         device_id: String,
@@ -208,6 +537,9 @@ impl AcsConnection {
             return Err(Box::from("Unknown ACS type"));
         }
 
+        // Replace instance selectors with instance numbers
+        let parameter_names = self.resolve_paths(device_id.clone(), parameter_names)?;
+
         // Define the URL
         let url = format!(
             "{}/devices?query=%7B%22_id%22%3A%22{}%22%7D&projection={}",
@@ -216,39 +548,33 @@ impl AcsConnection {
             parameter_names.join(",")
         );
 
-        if self.get_debug_log {
-            eprintln!("[get_parameter_values] URL: {}", url);
-            eprintln!("[get_parameter_values] device_id: {}", device_id);
-            eprintln!("[get_parameter_values] parameters: {:?}", parameter_names);
-        }
+        conn_event!(self, Level::DEBUG, parameters = ?parameter_names, "Request");
 
         // Send a GET request
-        let response = self.client.get(&url).send()?;
-
-        if self.get_debug_log {
-            eprintln!("[get_parameter_values] Status: {:?}", response.status());
-        }
+        let response = self.send(self.request(Method::GET, &url))?;
 
         if response.status().is_success() {
-            let s = response.text()?.clone();
-            if self.get_debug_log {
-                eprintln!("[get_parameter_values] Response body: {}", s);
-            }
+            let s = response.text()?;
+            conn_event!(self, Level::TRACE, body = %self.redactor().body(&s), "Response body");
             let json: Value = serde_json::from_str(&s)?;
             let root_device_array = json.as_array().unwrap();
-            if root_device_array.len() > 0 {
+            if !root_device_array.is_empty() {
                 let root_device = &root_device_array[0].clone();
                 if let Some(root_device_obj) = root_device.as_object() {
-                    if root_device_obj.contains_key("Device") {
-                        let device_node = &root_device_obj["Device"].clone();
-                        let device_node = self.parse_device_tree(&device_node);
-                        let mut root_node = DataNode {
-                            value: "".to_string(),
-                            value_type: "".to_string(),
-                            writable: false,
-                            subnodes: HashMap::new(),
-                        };
-                        root_node.subnodes.insert("Device".to_string(), device_node);
+                    let mut root_node = DataNode {
+                        value: "".to_string(),
+                        value_type: "".to_string(),
+                        writable: false,
+                        timestamp: "".to_string(),
+                        subnodes: HashMap::new(),
+                    };
+                    for root in PARAMETER_ROOTS {
+                        if let Some(node) = root_device_obj.get(*root) {
+                            let node = self.parse_device_tree(root, node);
+                            root_node.subnodes.insert(root.to_string(), node);
+                        }
+                    }
+                    if !root_node.subnodes.is_empty() {
                         return Ok(root_node);
                     }
                 }
@@ -263,6 +589,7 @@ impl AcsConnection {
         }
     }
 
+    #[instrument(name = "acs.refresh_object", skip_all, fields(device_id = %device_id))]
     pub fn refresh_object(
         &self,
         device_id: String,
@@ -272,6 +599,9 @@ impl AcsConnection {
             return Err(Box::from("Unknown ACS type"));
         }
 
+        // Replace instance selectors with instance numbers
+        let object = self.resolve_path(device_id.clone(), object)?;
+
         // Define the URL
         let url = format!(
             "{}/devices/{}/tasks?connection_request",
@@ -279,22 +609,11 @@ impl AcsConnection {
             self.encode_device(&device_id)
         );
 
-        let req = RefreshObject::new(object);
-
-        if self.debug_log {
-            eprintln!("[refresh_object] URL: {}", url);
-            eprintln!(
-                "[refresh_object] Request: {}",
-                serde_json::to_string(&req).unwrap()
-            );
-        }
+        let req = RefreshObject::new(&object);
 
+        conn_event!(self, Level::DEBUG, request = %self.redactor().json_string(&req), "Request");
         // Send a POST request
-        let response = self.client.post(&url).json(&req).send()?;
-
-        if self.debug_log {
-            eprintln!("[refresh_object] Status: {:?}", response.status());
-        }
+        let response = self.send(self.request(Method::POST, &url).json(&req)?)?;
 
         if response.status().is_success() {
             return Ok(());
@@ -306,6 +625,133 @@ impl AcsConnection {
         }
     }
 
+    /// Posts `req` as a task and asks the ACS to wait up to `timeout` for the
+    /// CPE to execute it. Returns `None` if the task completed and the id of
+    /// the queued task if it is still pending (CPE offline, unreachable or
+    /// faulted).
+    fn post_task_wait<T: serde::Serialize>(
+        &self,
+        device_id: &str,
+        req: &T,
+        timeout: Duration,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        // Define the URL
+        let url = format!(
+            "{}/devices/{}/tasks?timeout={}&connection_request",
+            self.addr,
+            self.encode_device(device_id),
+            timeout.as_millis()
+        );
+
+        conn_event!(self, Level::DEBUG, request = %self.redactor().json_string(req), "Request");
+
+        // Send a POST request; allow the ACS to hold it for the whole timeout
+        let response = self.send(
+            self.request(Method::POST, &url)
+                .timeout(self.timeout + timeout)
+                .json(req)?,
+        )?;
+
+        // 200 means the task has been executed, 202 means it is still queued
+        if response.status() == reqwest::StatusCode::OK {
+            return Ok(None);
+        } else if response.status().is_success() {
+            let task: Value = response.json()?;
+            let task_id = task
+                .get("_id")
+                .and_then(|v| v.as_str())
+                .ok_or("Queued task has no _id")?;
+            conn_event!(self, Level::DEBUG, task_id = %task_id, "Task still queued");
+            return Ok(Some(task_id.to_string()));
+        } else {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+    }
+
+    /// Same as `refresh_object`, but asks the ACS to wait up to `timeout` for
+    /// the CPE to execute the task. Returns `true` if the task completed and
+    /// `false` if it did not (CPE offline, unreachable or faulted). A refresh
+    /// not completed in time is deleted from the queue, so it does not pile
+    /// up with later attempts.
+    #[instrument(name = "acs.refresh_object_wait", skip_all, fields(device_id = %device_id))]
+    pub fn refresh_object_wait(
+        &self,
+        device_id: String,
+        object: &str,
+        timeout: Duration,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let object = self.resolve_path(device_id.clone(), object)?;
+        let req = RefreshObject::new(&object);
+        return match self.post_task_wait(&device_id, &req, timeout)? {
+            None => Ok(true),
+            Some(task_id) => {
+                self.delete_task(&task_id)?;
+                Ok(false)
+            }
+        };
+    }
+
+    /// Returns fresh values of `parameter_names`. Parameters whose
+    /// `_timestamp` in the ACS database is older than `max_age` are refreshed
+    /// from the CPE first, waiting up to `timeout` for each refresh. Fails if
+    /// the CPE did not complete a refresh, e.g. because it is offline; the
+    /// pending refresh is then removed from the queue.
+    #[instrument(name = "acs.refresh_parameter_values", skip_all, fields(device_id = %device_id))]
+    pub fn refresh_parameter_values(
+        &self,
+        device_id: String,
+        parameter_names: Vec<String>,
+        max_age: Duration,
+        timeout: Duration,
+    ) -> Result<DataNode, Box<dyn std::error::Error>> {
+        let parameter_names = self.resolve_paths(device_id.clone(), parameter_names)?;
+        let threshold = SystemTime::now()
+            .checked_sub(max_age)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        // A missing or unparsable cached copy simply means everything is stale
+        let cached = self
+            .get_parameter_values(device_id.clone(), parameter_names.clone())
+            .ok();
+
+        let stale: Vec<&String> = parameter_names
+            .iter()
+            .filter(
+                |name| match cached.as_ref().and_then(|c| c.get_node(name)) {
+                    Some(node) => is_stale(node, threshold),
+                    None => true,
+                },
+            )
+            .collect();
+
+        conn_event!(self, Level::DEBUG, stale = ?stale, "Stale parameters");
+
+        if stale.is_empty() {
+            if let Some(cached) = cached {
+                return Ok(cached);
+            }
+        }
+
+        for name in stale {
+            if !self.refresh_object_wait(device_id.clone(), name, timeout)? {
+                return Err(Box::from(format!(
+                    "Device {} did not complete refresh of {} within {:?}, it may be offline",
+                    device_id, name, timeout
+                )));
+            }
+        }
+
+        return self.get_parameter_values(device_id, parameter_names);
+    }
+
+    #[instrument(name = "acs.reboot", skip_all, fields(device_id = %device_id))]
     pub fn reboot(&self, device_id: String) -> Result<(), Box<dyn std::error::Error>> {
         if !matches!(self.acs_type, AcsType::GenieAcs) {
             return Err(Box::from("Unknown ACS type"));
@@ -320,17 +766,10 @@ impl AcsConnection {
 
         let req = SimpleCommand::new("reboot");
 
-        if self.debug_log {
-            eprintln!("[reboot] URL: {}", url);
-            eprintln!("[reboot] Request: {}", serde_json::to_string(&req).unwrap());
-        }
+        conn_event!(self, Level::DEBUG, request = %self.redactor().json_string(&req), "Request");
 
         // Send a POST request
-        let response = self.client.post(&url).json(&req).send()?;
-
-        if self.debug_log {
-            eprintln!("[reboot] Status: {:?}", response.status());
-        }
+        let response = self.send(self.request(Method::POST, &url).json(&req)?)?;
 
         if response.status().is_success() {
             return Ok(());
@@ -342,6 +781,7 @@ impl AcsConnection {
         }
     }
 
+    #[instrument(name = "acs.factory_reset", skip_all, fields(device_id = %device_id))]
     pub fn factory_reset(&self, device_id: String) -> Result<(), Box<dyn std::error::Error>> {
         if !matches!(self.acs_type, AcsType::GenieAcs) {
             return Err(Box::from("Unknown ACS type"));
@@ -356,20 +796,9 @@ impl AcsConnection {
 
         let req = SimpleCommand::new("factoryReset");
 
-        if self.debug_log {
-            eprintln!("[factory_reset] URL: {}", url);
-            eprintln!(
-                "[factory_reset] Request: {}",
-                serde_json::to_string(&req).unwrap()
-            );
-        }
-
+        conn_event!(self, Level::DEBUG, request = %self.redactor().json_string(&req), "Request");
         // Send a POST request
-        let response = self.client.post(&url).json(&req).send()?;
-
-        if self.debug_log {
-            eprintln!("[factory_reset] Status: {:?}", response.status());
-        }
+        let response = self.send(self.request(Method::POST, &url).json(&req)?)?;
 
         if response.status().is_success() {
             return Ok(());
@@ -381,6 +810,7 @@ impl AcsConnection {
         }
     }
 
+    #[instrument(name = "acs.add_del_object", skip_all, fields(device_id = %device_id))]
     pub fn add_del_object(
         &self,
         device_id: String,
@@ -391,6 +821,9 @@ impl AcsConnection {
             return Err(Box::from("Unknown ACS type"));
         }
 
+        // Replace instance selectors with instance numbers
+        let object_name = self.resolve_path(device_id.clone(), &object_name)?;
+
         // Define the URL
         let url = format!(
             "{}/devices/{}/tasks?connection_request",
@@ -398,36 +831,10 @@ impl AcsConnection {
             self.encode_device(&device_id)
         );
 
-        if self.debug_log {
-            eprintln!("[add_del_object] URL: {}", url);
-        }
         let req = AddDeleteObject::new(add, &object_name);
-        if self.debug_log {
-            eprintln!(
-                "[add_del_object] Request: {}",
-                serde_json::to_string(&req).unwrap()
-            );
-        }
-
+        conn_event!(self, Level::DEBUG, request = %self.redactor().json_string(&req), "Request");
         // Send a POST request
-        let response = self.client.post(&url).json(&req).send();
-        match response {
-            Ok(ref _resp) => {}
-            Err(err) => {
-                if self.debug_log {
-                    eprintln!(
-                        "[add_del_object] HTTP error while sending request: {:?}",
-                        err
-                    );
-                }
-                return Err(Box::from(err));
-            }
-        };
-
-        let response = response.unwrap();
-        if self.debug_log {
-            eprintln!("[add_del_object] Status: {:?}", response.status());
-        }
+        let response = self.send(self.request(Method::POST, &url).json(&req)?)?;
 
         if response.status().is_success() {
             return Ok(());
@@ -439,25 +846,90 @@ impl AcsConnection {
         }
     }
 
-    pub fn del_device(&self, device_id: String) -> Result<(), Box<dyn std::error::Error>> {
+    /// Creates a new instance of `object_name` (e.g. `Device.NAT.PortMapping`)
+    /// and returns its full path, e.g. `Device.NAT.PortMapping.3`. The new
+    /// instance number is found by comparing the instances present before
+    /// and after the addObject task. `parameter_values` are then set on the
+    /// new instance; their names are relative to it (e.g. `Enable`).
+    #[instrument(name = "acs.add_object", skip_all, fields(device_id = %device_id))]
+    pub fn add_object(
+        &self,
+        device_id: String,
+        object_name: String,
+        parameter_values: Vec<ParameterValue>,
+        timeout: Duration,
+    ) -> Result<String, Box<dyn std::error::Error>> {
         if !matches!(self.acs_type, AcsType::GenieAcs) {
             return Err(Box::from("Unknown ACS type"));
         }
 
-        // Define the URL
-        let url = format!("{}/devices/{}", self.addr, self.encode_device(&device_id));
+        let object_name = self.resolve_path(device_id.clone(), &object_name)?;
+        let object_name = object_name.trim_end_matches('.').to_string();
+        let instances = |conn: &Self| -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+            if !conn.refresh_object_wait(device_id.clone(), &object_name, timeout)? {
+                return Err(Box::from(format!(
+                    "Device {} did not complete refresh of {} within {:?}, it may be offline",
+                    device_id, object_name, timeout
+                )));
+            }
+            let tree = conn.get_parameter_values(device_id.clone(), vec![object_name.clone()])?;
+            return Ok(tree
+                .get_node(&object_name)
+                .map(|node| node.instance_numbers())
+                .unwrap_or_default());
+        };
+
+        let before = instances(self)?;
 
-        if self.debug_log {
-            eprintln!("[del_device] URL: {}", url);
+        let req = AddDeleteObject::new(true, &object_name);
+        if let Some(task_id) = self.post_task_wait(&device_id, &req, timeout)? {
+            return Err(Box::from(format!(
+                "Device {} did not complete addObject of {} within {:?}, it may be offline; the task {} is still queued",
+                device_id, object_name, timeout, task_id
+            )));
         }
 
-        // Send a DELETE request
-        let response = self.client.delete(&url).send()?;
+        let after = instances(self)?;
+        let created: Vec<&u32> = after.iter().filter(|i| !before.contains(i)).collect();
+        if created.len() != 1 {
+            return Err(Box::from(format!(
+                "Cannot determine the instance created under {}: new instances {:?}",
+                object_name, created
+            )));
+        }
+
+        let path = format!("{}.{}", object_name, created[0]);
+        conn_event!(self, Level::DEBUG, path = %path, "Created instance");
+
+        if !parameter_values.is_empty() {
+            let parameter_values = parameter_values
+                .iter()
+                .map(|pv| {
+                    ParameterValue::new(
+                        &format!("{}.{}", path, pv.parameter),
+                        &pv.value,
+                        &pv.value_type,
+                    )
+                })
+                .collect();
+            self.set_parameter_values(device_id, parameter_values)?;
+        }
 
-        if self.debug_log {
-            eprintln!("[del_device] Status: {:?}", response.status());
+        return Ok(path);
+    }
+
+    #[instrument(name = "acs.del_device", skip_all, fields(device_id = %device_id))]
+    pub fn del_device(&self, device_id: String) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
         }
 
+        // Define the URL
+        let url = format!("{}/devices/{}", self.addr, self.encode_device(&device_id));
+
+        // Send a DELETE request
+        let response = self.send(self.request(Method::DELETE, &url))?;
+
         if response.status().is_success() {
             return Ok(());
         } else {
@@ -468,6 +940,7 @@ impl AcsConnection {
         }
     }
 
+    #[instrument(name = "acs.add_del_tag", skip_all, fields(device_id = %device_id))]
     pub fn add_del_tag(
         &self,
         device_id: String,
@@ -486,22 +959,15 @@ impl AcsConnection {
             tag
         );
 
-        if self.debug_log {
-            eprintln!("[add_del_tag] URL: {}", url);
-            eprintln!("[add_del_tag] add={} tag={}", add, tag);
-        }
+        conn_event!(self, Level::DEBUG, add, tag = %tag, "Request");
 
         // Send a POST/DELETE request
         let response = if add {
-            self.client.post(&url).send()?
+            self.send(self.request(Method::POST, &url))?
         } else {
-            self.client.delete(&url).send()?
+            self.send(self.request(Method::DELETE, &url))?
         };
 
-        if self.debug_log {
-            eprintln!("[add_del_tag] Status: {:?}", response.status());
-        }
-
         if response.status().is_success() {
             return Ok(());
         } else {
@@ -512,6 +978,7 @@ impl AcsConnection {
         }
     }
 
+    #[instrument(name = "acs.upload_file", skip_all, fields(name = %name))]
     pub fn upload_file(
         &self,
         name: &str,
@@ -521,66 +988,262 @@ impl AcsConnection {
         product_class: &str,
         version: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let metadata = AcsFileMetadata::new(FileType::from(file_type))
+            .oui(oui)
+            .product_class(product_class)
+            .version(version);
+        self.upload_path(
+            name,
+            std::path::Path::new(path),
+            &metadata,
+            &TransferOptions::default(),
+        )?;
+        return Ok(());
+    }
+
+    /// Uploads the file at `path`, streaming it from disk.
+    pub fn upload_path(
+        &self,
+        name: &str,
+        path: &std::path::Path,
+        metadata: &AcsFileMetadata,
+        options: &TransferOptions,
+    ) -> Result<TransferReport, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path)?;
+        let length = file.metadata()?.len();
+        return self.upload(name, file, Some(length), metadata, options);
+    }
+
+    /// Streams `reader` into the file store as `name`, computing MD5 and
+    /// SHA-256 on the way.
+    ///
+    /// Once stored, the length (and MD5, if the file store records one) is
+    /// checked against what was sent, as are the expected digests from
+    /// `options`. On any mismatch the stored file is deleted, unless
+    /// `options.keep_unverified` is set, and an error listing the
+    /// differences is returned. Any previous file of that name has already
+    /// been replaced by the upload either way.
+    #[instrument(name = "acs.upload", skip_all, fields(name = %name, length = ?length))]
+    pub fn upload<R: std::io::Read + Send + 'static>(
+        &self,
+        name: &str,
+        reader: R,
+        length: Option<u64>,
+        metadata: &AcsFileMetadata,
+        options: &TransferOptions,
+    ) -> Result<TransferReport, Box<dyn std::error::Error>> {
         if !matches!(self.acs_type, AcsType::GenieAcs) {
             return Err(Box::from("Unknown ACS type"));
         }
 
         // Define the URL
-        let url = format!("{}/files/{}", self.addr, name);
+        let url = format!("{}/files/{}", self.addr, encode(name));
 
         // Create set of headers
         let mut headers = HeaderMap::new();
-        headers.insert("fileType", HeaderValue::from_str(file_type)?);
-        headers.insert("oui", HeaderValue::from_str(oui)?);
-        headers.insert("productClass", HeaderValue::from_str(product_class)?);
-        headers.insert("version", HeaderValue::from_str(version)?);
+        headers.insert(
+            "fileType",
+            HeaderValue::from_str(metadata.file_type.as_str())?,
+        );
+        headers.insert("oui", HeaderValue::from_str(&metadata.oui)?);
+        headers.insert(
+            "productClass",
+            HeaderValue::from_str(&metadata.product_class)?,
+        );
+        headers.insert("version", HeaderValue::from_str(&metadata.version)?);
 
-        // Read file
-        let file_bytes = std::fs::read(path)?;
+        let reader = HashingReader::new(reader, length, options.progress.clone());
+        let digests = reader.digests();
 
-        if self.debug_log {
-            eprintln!("[upload_file] URL: {}", url);
-            eprintln!("[upload_file] File bytes: {:?}", file_bytes.len());
-        }
         // Send request
-        let response = self
-            .client
-            .put(&url)
-            .headers(headers)
-            .body(file_bytes)
-            .send()?;
-        if self.debug_log {
-            eprintln!("[upload_file] Status: {:?}", response.status());
-        }
+        let response = self.send(
+            self.request(Method::PUT, &url)
+                .timeout_opt(options.timeout)
+                .headers(headers)
+                .body_reader(Box::new(reader), length),
+        )?;
 
-        if response.status().is_success() {
-            return Ok(());
-        } else {
+        if !response.status().is_success() {
             return Err(Box::from(format!(
                 "Response indicates failure: {}",
                 response.status()
             )));
         }
-    }
 
-    pub fn delete_file(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        if !matches!(self.acs_type, AcsType::GenieAcs) {
-            return Err(Box::from("Unknown ACS type"));
+        let report = {
+            let digests = digests.lock().unwrap();
+            let (md5, sha256) = digests.finish();
+            TransferReport {
+                name: name.to_string(),
+                length: digests.length,
+                md5,
+                sha256,
+            }
+        };
+        conn_event!(self, Level::DEBUG, length = report.length, md5 = %report.md5, "Uploaded");
+
+        let mut problems = Vec::new();
+        if let Some(expected) = length {
+            if expected != report.length {
+                problems.push(format!(
+                    "read {} bytes, expected {}",
+                    report.length, expected
+                ));
+            }
+        }
+        match self.get_file(name)? {
+            Some(stored) => problems.extend(report.mismatches(options, &stored)),
+            None => problems.push("file missing from the file store".to_string()),
         }
 
-        // Define the URL
-        let url = format!("{}/files/{}", self.addr, name);
-
-        if self.debug_log {
-            eprintln!("[delete_file] URL: {}", url);
+        if !problems.is_empty() {
+            let outcome = if options.keep_unverified {
+                "the stored file was kept".to_string()
+            } else {
+                match self.delete_file(name) {
+                    Ok(()) => "the stored file was deleted".to_string(),
+                    Err(err) => format!("deleting the stored file failed ({})", err),
+                }
+            };
+            return Err(Box::from(format!(
+                "Upload of {} failed verification, {}: {}",
+                name,
+                outcome,
+                problems.join("; ")
+            )));
         }
 
-        // Send request
-        let response = self.client.delete(&url).send()?;
+        return Ok(report);
+    }
 
-        if self.debug_log {
-            eprintln!("[delete_file] Status: {:?}", response.status());
+    /// Address of the file server: the configured one, or else the NBI
+    /// host on the default file server port. The NBI itself does not serve
+    /// file contents.
+    fn file_server_addr(&self) -> Result<String, Box<dyn std::error::Error>> {
+        if let Some(file_server) = &self.file_server {
+            return Ok(file_server.trim_end_matches('/').to_string());
         }
+        let mut url = reqwest::Url::parse(&self.addr)
+            .ok()
+            .filter(|url| url.has_host())
+            .ok_or_else(|| {
+                format!(
+                    "No file server configured and none can be derived from {}",
+                    self.addr
+                )
+            })?;
+        url.set_port(Some(DEFAULT_FILE_SERVER_PORT))
+            .map_err(|_| format!("Cannot derive a file server address from {}", self.addr))?;
+        url.set_path("");
+        return Ok(url.as_str().trim_end_matches('/').to_string());
+    }
+
+    /// Request for the content of file `name` from the file server, with
+    /// the file server's own credentials.
+    fn file_request(&self, name: &str) -> Result<HttpRequest, Box<dyn std::error::Error>> {
+        let url = format!("{}/{}", self.file_server_addr()?, encode(name));
+        return Ok(self.request_with_auth(Method::GET, &url, self.file_server_auth.as_ref()));
+    }
+
+    /// Streams the content of file `name` into `writer`, computing MD5 and
+    /// SHA-256 on the way.
+    ///
+    /// The content is checked against the stored length and MD5 (if the
+    /// file store records one) and the expected digests from `options`.
+    /// Data already written is not taken back on a mismatch; use
+    /// `fetch_file_to_path` for that.
+    #[instrument(name = "acs.fetch_file", skip_all, fields(name = %name))]
+    pub fn fetch_file(
+        &self,
+        name: &str,
+        writer: &mut dyn std::io::Write,
+        options: &TransferOptions,
+    ) -> Result<TransferReport, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let stored = self
+            .get_file(name)?
+            .ok_or(format!("File {} not found", name))?;
+
+        // Send request
+        let response = self.send(self.file_request(name)?.timeout_opt(options.timeout))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+
+        let mut reader =
+            HashingReader::new(response.body, Some(stored.length), options.progress.clone());
+        let digests = reader.digests();
+        std::io::copy(&mut reader, writer)?;
+        writer.flush()?;
+
+        let report = {
+            let digests = digests.lock().unwrap();
+            let (md5, sha256) = digests.finish();
+            TransferReport {
+                name: name.to_string(),
+                length: digests.length,
+                md5,
+                sha256,
+            }
+        };
+        conn_event!(self, Level::DEBUG, length = report.length, md5 = %report.md5, "Fetched");
+
+        let problems = report.mismatches(options, &stored);
+        if !problems.is_empty() {
+            return Err(Box::from(format!(
+                "Download of {} failed verification: {}",
+                name,
+                problems.join("; ")
+            )));
+        }
+        return Ok(report);
+    }
+
+    /// Downloads file `name` to `path`. The content is written next to
+    /// `path` first and only moved into place once verified.
+    pub fn fetch_file_to_path(
+        &self,
+        name: &str,
+        path: &std::path::Path,
+        options: &TransferOptions,
+    ) -> Result<TransferReport, Box<dyn std::error::Error>> {
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".part");
+        let partial = std::path::PathBuf::from(partial);
+
+        let result = std::fs::File::create(&partial)
+            .map_err(Box::<dyn std::error::Error>::from)
+            .and_then(|mut file| self.fetch_file(name, &mut file, options));
+        match result {
+            Ok(report) => {
+                std::fs::rename(&partial, path)?;
+                return Ok(report);
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&partial);
+                return Err(e);
+            }
+        }
+    }
+
+    #[instrument(name = "acs.delete_file", skip_all, fields(name = %name))]
+    pub fn delete_file(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        // Define the URL
+        let url = format!("{}/files/{}", self.addr, encode(name));
+
+        // Send request
+        let response = self.send(self.request(Method::DELETE, &url))?;
 
         if response.status().is_success() {
             return Ok(());
@@ -592,10 +1255,13 @@ impl AcsConnection {
         }
     }
 
+    /// Queues a download task, given either a `DownloadCommand` or just the
+    /// name of a file in the file store.
+    #[instrument(name = "acs.download", skip_all, fields(device_id = %device_id))]
     pub fn download(
         &self,
         device_id: String,
-        filename: String,
+        command: impl Into<DownloadCommand>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         if !matches!(self.acs_type, AcsType::GenieAcs) {
             return Err(Box::from("Unknown ACS type"));
@@ -608,21 +1274,11 @@ impl AcsConnection {
             self.encode_device(&device_id)
         );
 
-        let req = DownloadCommand::new(&filename);
-
-        if self.debug_log {
-            eprintln!("[download] URL: {}", url);
-            eprintln!(
-                "[download] Request: {}",
-                serde_json::to_string(&req).unwrap()
-            );
-        }
+        let req: DownloadCommand = command.into();
 
+        conn_event!(self, Level::DEBUG, request = %self.redactor().json_string(&req), "Request");
         // Send a POST request
-        let response = self.client.post(&url).json(&req).send()?;
-        if self.debug_log {
-            eprintln!("[download] Status: {:?}", response.status());
-        }
+        let response = self.send(self.request(Method::POST, &url).json(&req)?)?;
 
         if response.status().is_success() {
             return Ok(());
@@ -634,6 +1290,7 @@ impl AcsConnection {
         }
     }
 
+    #[instrument(name = "acs.list_tasks", skip_all, fields(device_id = %device_id))]
     pub fn list_tasks(&self, device_id: &str) -> Result<Vec<AcsTask>, Box<dyn std::error::Error>> {
         if !matches!(self.acs_type, AcsType::GenieAcs) {
             return Err(Box::from("Unknown ACS type"));
@@ -645,16 +1302,56 @@ impl AcsConnection {
             self.encode_device(device_id)
         );
 
-        if self.debug_log {
-            eprintln!("[list_tasks] URL: {}", url);
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
         }
 
-        let response = self.client.get(&url).send()?;
+        let tasks: Vec<AcsTask> = response.json()?;
+
+        conn_event!(self, Level::DEBUG, tasks = tasks.len(), "Response");
+
+        Ok(tasks)
+    }
 
-        if self.debug_log {
-            eprintln!("[list_tasks] Status: {:?}", response.status());
+    #[instrument(name = "acs.delete_task", skip_all, fields(task_id = %task_id))]
+    pub fn delete_task(&self, task_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
         }
 
+        let url = format!("{}/tasks/{}", self.addr, task_id);
+
+        let response = self.send(self.request(Method::DELETE, &url))?;
+
+        if response.status().is_success() {
+            return Ok(());
+        } else {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+    }
+
+    #[instrument(name = "acs.list_faults", skip_all, fields(device_id = %device_id))]
+    pub fn list_faults(
+        &self,
+        device_id: &str,
+    ) -> Result<Vec<AcsFault>, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let query = serde_json::json!({ "device": device_id }).to_string();
+        let url = format!("{}/faults?query={}", self.addr, encode(&query));
+
+        let response = self.send(self.request(Method::GET, &url))?;
+
         if !response.status().is_success() {
             return Err(Box::from(format!(
                 "Response indicates failure: {}",
@@ -662,32 +1359,101 @@ impl AcsConnection {
             )));
         }
 
-        let tasks: Vec<AcsTask> = response.json()?;
+        let faults: Vec<AcsFault> = response.json()?;
+
+        conn_event!(self, Level::DEBUG, faults = faults.len(), "Response");
 
-        if self.debug_log {
-            eprintln!("[list_tasks] Got {} task(s)", tasks.len());
+        Ok(faults)
+    }
+
+    /// Deletes a fault, which lets the ACS retry the faulted task.
+    #[instrument(name = "acs.delete_fault", skip_all, fields(fault_id = %fault_id))]
+    pub fn delete_fault(&self, fault_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
         }
 
-        Ok(tasks)
+        let url = format!("{}/faults/{}", self.addr, encode(fault_id));
+
+        let response = self.send(self.request(Method::DELETE, &url))?;
+
+        if response.status().is_success() {
+            return Ok(());
+        } else {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
     }
 
-    pub fn delete_task(&self, task_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    #[instrument(name = "acs.list_presets", skip_all)]
+    pub fn list_presets(&self) -> Result<Vec<AcsPreset>, Box<dyn std::error::Error>> {
         if !matches!(self.acs_type, AcsType::GenieAcs) {
             return Err(Box::from("Unknown ACS type"));
         }
 
-        let url = format!("{}/tasks/{}", self.addr, task_id);
+        let url = format!("{}/presets", self.addr);
 
-        if self.debug_log {
-            eprintln!("[delete_task] URL: {}", url);
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
         }
 
-        let response = self.client.delete(&url).send()?;
+        let presets: Vec<AcsPreset> = response.json()?;
+
+        conn_event!(self, Level::DEBUG, presets = presets.len(), "Response");
+
+        Ok(presets)
+    }
 
-        if self.debug_log {
-            eprintln!("[delete_task] Status: {:?}", response.status());
+    #[instrument(name = "acs.get_preset", skip_all, fields(name = %name))]
+    pub fn get_preset(&self, name: &str) -> Result<Option<AcsPreset>, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
         }
 
+        let query = serde_json::json!({ "_id": name }).to_string();
+        let url = format!("{}/presets?query={}", self.addr, encode(&query));
+
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+
+        let presets: Vec<AcsPreset> = response.json()?;
+        return Ok(presets.into_iter().next());
+    }
+
+    /// Creates the preset, or replaces the preset with the same name.
+    #[instrument(name = "acs.put_preset", skip_all, fields(name = %preset.name))]
+    pub fn put_preset(&self, preset: &AcsPreset) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+        if preset.name.is_empty() {
+            return Err(Box::from("Preset has no name"));
+        }
+
+        let url = format!("{}/presets/{}", self.addr, encode(&preset.name));
+
+        // The name is part of the URL, not of the document
+        let mut req = serde_json::to_value(preset)?;
+        if let Some(obj) = req.as_object_mut() {
+            obj.remove("_id");
+        }
+
+        conn_event!(self, Level::DEBUG, request = %self.redactor().json_string(&req), "Request");
+        let response = self.send(self.request(Method::PUT, &url).json(&req)?)?;
+
         if response.status().is_success() {
             return Ok(());
         } else {
@@ -697,4 +1463,416 @@ impl AcsConnection {
             )));
         }
     }
+
+    #[instrument(name = "acs.delete_preset", skip_all, fields(name = %name))]
+    pub fn delete_preset(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let url = format!("{}/presets/{}", self.addr, encode(name));
+
+        let response = self.send(self.request(Method::DELETE, &url))?;
+
+        if response.status().is_success() {
+            return Ok(());
+        } else {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+    }
+
+    #[instrument(name = "acs.list_provisions", skip_all)]
+    pub fn list_provisions(&self) -> Result<Vec<AcsProvision>, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let url = format!("{}/provisions", self.addr);
+
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+
+        let provisions: Vec<AcsProvision> = response.json()?;
+
+        conn_event!(
+            self,
+            Level::DEBUG,
+            provisions = provisions.len(),
+            "Response"
+        );
+
+        Ok(provisions)
+    }
+
+    #[instrument(name = "acs.get_provision", skip_all, fields(name = %name))]
+    pub fn get_provision(
+        &self,
+        name: &str,
+    ) -> Result<Option<AcsProvision>, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let query = serde_json::json!({ "_id": name }).to_string();
+        let url = format!("{}/provisions?query={}", self.addr, encode(&query));
+
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+
+        let provisions: Vec<AcsProvision> = response.json()?;
+        return Ok(provisions.into_iter().next());
+    }
+
+    /// Creates the provision, or replaces the script of the provision with
+    /// the same name. GenieACS rejects scripts that do not compile.
+    #[instrument(name = "acs.put_provision", skip_all, fields(name = %provision.name))]
+    pub fn put_provision(
+        &self,
+        provision: &AcsProvision,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+        if provision.name.is_empty() {
+            return Err(Box::from("Provision has no name"));
+        }
+
+        let url = format!("{}/provisions/{}", self.addr, encode(&provision.name));
+
+        conn_event!(
+            self,
+            Level::DEBUG,
+            script_bytes = provision.script.len(),
+            "Request"
+        );
+        let response = self.send(
+            self.request(Method::PUT, &url)
+                .header("Content-Type", "application/javascript")?
+                .body(provision.script.clone().into_bytes()),
+        )?;
+
+        if response.status().is_success() {
+            return Ok(());
+        } else {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            return Err(Box::from(format!(
+                "Response indicates failure: {} {}",
+                status,
+                body.trim()
+            )));
+        }
+    }
+
+    #[instrument(name = "acs.delete_provision", skip_all, fields(name = %name))]
+    pub fn delete_provision(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let url = format!("{}/provisions/{}", self.addr, encode(name));
+
+        let response = self.send(self.request(Method::DELETE, &url))?;
+
+        if response.status().is_success() {
+            return Ok(());
+        } else {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+    }
+
+    #[instrument(name = "acs.list_virtual_parameters", skip_all)]
+    pub fn list_virtual_parameters(
+        &self,
+    ) -> Result<Vec<AcsVirtualParameter>, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let url = format!("{}/virtual_parameters", self.addr);
+
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+
+        let virtual_parameters: Vec<AcsVirtualParameter> = response.json()?;
+
+        conn_event!(
+            self,
+            Level::DEBUG,
+            virtual_parameters = virtual_parameters.len(),
+            "Response"
+        );
+
+        Ok(virtual_parameters)
+    }
+
+    #[instrument(name = "acs.get_virtual_parameter", skip_all, fields(name = %name))]
+    pub fn get_virtual_parameter(
+        &self,
+        name: &str,
+    ) -> Result<Option<AcsVirtualParameter>, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let query = serde_json::json!({ "_id": name }).to_string();
+        let url = format!("{}/virtual_parameters?query={}", self.addr, encode(&query));
+
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+
+        let virtual_parameters: Vec<AcsVirtualParameter> = response.json()?;
+        return Ok(virtual_parameters.into_iter().next());
+    }
+
+    /// Creates the virtual parameter, or replaces the script of the one
+    /// with the same name.
+    #[instrument(name = "acs.put_virtual_parameter", skip_all, fields(name = %virtual_parameter.name))]
+    pub fn put_virtual_parameter(
+        &self,
+        virtual_parameter: &AcsVirtualParameter,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+        if virtual_parameter.name.is_empty() {
+            return Err(Box::from("Virtual parameter has no name"));
+        }
+
+        let url = format!(
+            "{}/virtual_parameters/{}",
+            self.addr,
+            encode(&virtual_parameter.name)
+        );
+
+        conn_event!(
+            self,
+            Level::DEBUG,
+            script_bytes = virtual_parameter.script.len(),
+            "Request"
+        );
+        let response = self.send(
+            self.request(Method::PUT, &url)
+                .header("Content-Type", "application/javascript")?
+                .body(virtual_parameter.script.clone().into_bytes()),
+        )?;
+
+        if response.status().is_success() {
+            return Ok(());
+        } else {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            return Err(Box::from(format!(
+                "Response indicates failure: {} {}",
+                status,
+                body.trim()
+            )));
+        }
+    }
+
+    #[instrument(name = "acs.delete_virtual_parameter", skip_all, fields(name = %name))]
+    pub fn delete_virtual_parameter(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let url = format!("{}/virtual_parameters/{}", self.addr, encode(name));
+
+        let response = self.send(self.request(Method::DELETE, &url))?;
+
+        if response.status().is_success() {
+            return Ok(());
+        } else {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+    }
+
+    /// Virtual parameters present on a device, keyed by name, as last
+    /// computed by the ACS. Empty if the device has none. Single values can
+    /// also be read with `get_parameter_values`, e.g. `VirtualParameters.wan_ip`.
+    #[instrument(name = "acs.get_device_virtual_parameters", skip_all, fields(device_id = %device_id))]
+    pub fn get_device_virtual_parameters(
+        &self,
+        device_id: String,
+    ) -> Result<DataNode, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let query = serde_json::json!({ "_id": device_id }).to_string();
+        let url = format!(
+            "{}/devices?query={}&projection=VirtualParameters",
+            self.addr,
+            encode(&query)
+        );
+
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+
+        let devices: Vec<Value> = response.json()?;
+        let device = devices
+            .first()
+            .ok_or_else(|| format!("Device {} not found", device_id))?;
+        return Ok(match device.get("VirtualParameters") {
+            Some(node) => self.parse_device_tree("VirtualParameters", node),
+            None => DataNode::new(),
+        });
+    }
+
+    /// Raw documents of an NBI collection such as `files` or `presets`.
+    pub(crate) fn get_collection(
+        &self,
+        collection: &str,
+    ) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let url = format!("{}/{}", self.addr, collection);
+
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+
+        let docs: Vec<Value> = response.json()?;
+        return Ok(docs);
+    }
+
+    /// ACS configuration entries (`/config`), e.g.
+    /// `cwmp.connectionRequestAllowBasicAuth`, with their values.
+    #[instrument(name = "acs.list_config", skip_all)]
+    pub fn list_config(&self) -> Result<BTreeMap<String, Value>, Box<dyn std::error::Error>> {
+        let docs = self.get_collection("config")?;
+
+        conn_event!(self, Level::DEBUG, config = docs.len(), "Response");
+
+        return Ok(docs
+            .into_iter()
+            .filter_map(|doc| {
+                let key = doc.get("_id")?.as_str()?.to_string();
+                Some((key, doc.get("value").cloned().unwrap_or(Value::Null)))
+            })
+            .collect());
+    }
+
+    #[instrument(name = "acs.list_files", skip_all)]
+    pub fn list_files(&self) -> Result<Vec<AcsFile>, Box<dyn std::error::Error>> {
+        return self.list_files_query("{}");
+    }
+
+    /// Files matching a GenieACS query, e.g.
+    /// `{"metadata.productClass":"HG8245"}`.
+    #[instrument(name = "acs.list_files_query", skip_all, fields(query = %query))]
+    pub fn list_files_query(
+        &self,
+        query: &str,
+    ) -> Result<Vec<AcsFile>, Box<dyn std::error::Error>> {
+        if !matches!(self.acs_type, AcsType::GenieAcs) {
+            return Err(Box::from("Unknown ACS type"));
+        }
+
+        let url = format!("{}/files?query={}", self.addr, encode(query));
+
+        let response = self.send(self.request(Method::GET, &url))?;
+
+        if !response.status().is_success() {
+            return Err(Box::from(format!(
+                "Response indicates failure: {}",
+                response.status()
+            )));
+        }
+
+        let files: Vec<AcsFile> = response.json()?;
+
+        conn_event!(self, Level::DEBUG, files = files.len(), "Response");
+
+        Ok(files)
+    }
+
+    /// The firmware image with the highest version among those uploaded
+    /// for `oui` and `product_class`, `None` if there is none. Ties go to
+    /// the most recent upload.
+    #[instrument(name = "acs.latest_firmware", skip_all, fields(oui = %oui, product_class = %product_class))]
+    pub fn latest_firmware(
+        &self,
+        oui: &str,
+        product_class: &str,
+    ) -> Result<Option<AcsFile>, Box<dyn std::error::Error>> {
+        let query = serde_json::json!({
+            "metadata.fileType": FileType::FirmwareUpgradeImage.as_str(),
+            "metadata.oui": oui,
+            "metadata.productClass": product_class,
+        })
+        .to_string();
+        let files = self.list_files_query(&query)?;
+        return Ok(files.into_iter().max_by(|a, b| {
+            compare_versions(&a.metadata.version, &b.metadata.version)
+                .then_with(|| a.upload_date.cmp(&b.upload_date))
+        }));
+    }
+
+    /// `latest_firmware` for the OUI and product class of a device.
+    pub fn latest_firmware_for_device(
+        &self,
+        device_id: &str,
+    ) -> Result<Option<AcsFile>, Box<dyn std::error::Error>> {
+        let query = serde_json::json!({ "_id": device_id }).to_string();
+        let device = self
+            .list_devices_query(&query)?
+            .into_iter()
+            .next()
+            .ok_or(format!("Device {} not found", device_id))?;
+        return self.latest_firmware(&device.device_id.oui, &device.device_id.product_class);
+    }
+
+    /// Metadata of a file, `None` if there is no file with that name.
+    #[instrument(name = "acs.get_file", skip_all, fields(name = %name))]
+    pub fn get_file(&self, name: &str) -> Result<Option<AcsFile>, Box<dyn std::error::Error>> {
+        let query = serde_json::json!({ "_id": name }).to_string();
+        return Ok(self.list_files_query(&query)?.into_iter().next());
+    }
 }
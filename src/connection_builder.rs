@@ -0,0 +1,258 @@
+use crate::acs_type::*;
+use crate::connection::AcsConnection;
+use crate::middleware::Middleware;
+use crate::redact::Redactor;
+use crate::retry::RetryPolicy;
+use crate::transport::{HttpTransport, ReqwestTransport};
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Identity, Proxy};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Credentials sent with every request to the ACS.
+#[derive(PartialEq, Clone)]
+pub enum AcsAuth {
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    Bearer(String),
+}
+
+impl std::fmt::Debug for AcsAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AcsAuth::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"***")
+                .finish(),
+            AcsAuth::Bearer(_) => f.debug_tuple("Bearer").field(&"***").finish(),
+        }
+    }
+}
+
+/// Builder for `AcsConnection` with authentication, TLS, proxy and timeout
+/// settings. Invalid settings are reported by `build` rather than panicking.
+pub struct AcsConnectionBuilder {
+    acs_type: AcsType,
+    addr: String,
+    auth: Option<AcsAuth>,
+    root_certificates: Vec<Vec<u8>>,
+    builtin_root_certificates: bool,
+    client_identity: Option<Vec<u8>>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+    default_headers: Vec<(String, String)>,
+    timeout: Duration,
+    connect_timeout: Duration,
+    pool_idle_timeout: Duration,
+    pool_max_idle_per_host: usize,
+    retry_policy: RetryPolicy,
+    middleware: Vec<Arc<dyn Middleware>>,
+    transport: Option<Arc<dyn HttpTransport>>,
+    file_server: Option<String>,
+    file_server_auth: Option<AcsAuth>,
+    redactor: Option<Redactor>,
+}
+
+impl AcsConnectionBuilder {
+    pub fn new(acs_type: AcsType, addr: String) -> Self {
+        return AcsConnectionBuilder {
+            acs_type,
+            addr,
+            auth: None,
+            root_certificates: Vec::new(),
+            builtin_root_certificates: true,
+            client_identity: None,
+            proxy: None,
+            user_agent: None,
+            default_headers: Vec::new(),
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle_per_host: 10,
+            retry_policy: RetryPolicy::default(),
+            middleware: Vec::new(),
+            transport: None,
+            file_server: None,
+            file_server_auth: None,
+            redactor: None,
+        };
+    }
+
+    /// HTTP basic authentication
+    pub fn basic_auth(mut self, username: &str, password: Option<&str>) -> Self {
+        self.auth = Some(AcsAuth::Basic {
+            username: username.to_string(),
+            password: password.map(String::from),
+        });
+        return self;
+    }
+
+    /// `Authorization: Bearer <token>` authentication
+    pub fn bearer_auth(mut self, token: &str) -> Self {
+        self.auth = Some(AcsAuth::Bearer(token.to_string()));
+        return self;
+    }
+
+    /// Trusts an additional root CA, given as PEM.
+    pub fn add_root_certificate_pem(mut self, pem: &[u8]) -> Self {
+        self.root_certificates.push(pem.to_vec());
+        return self;
+    }
+
+    /// Whether the bundled public root CAs are trusted (default: `true`).
+    /// Disable to trust only certificates added with `add_root_certificate_pem`.
+    pub fn builtin_root_certificates(mut self, enabled: bool) -> Self {
+        self.builtin_root_certificates = enabled;
+        return self;
+    }
+
+    /// Client certificate for mutual TLS, given as PEM containing both the
+    /// certificate chain and the private key.
+    pub fn client_identity_pem(mut self, pem: &[u8]) -> Self {
+        self.client_identity = Some(pem.to_vec());
+        return self;
+    }
+
+    /// Sends all requests through the given HTTP(S) proxy URL.
+    pub fn proxy(mut self, url: &str) -> Self {
+        self.proxy = Some(url.to_string());
+        return self;
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        return self;
+    }
+
+    /// Adds a header sent with every request.
+    pub fn default_header(mut self, name: &str, value: &str) -> Self {
+        self.default_headers
+            .push((name.to_string(), value.to_string()));
+        return self;
+    }
+
+    /// Total timeout of a single request
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        return self;
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        return self;
+    }
+
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = timeout;
+        return self;
+    }
+
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = max;
+        return self;
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        return self;
+    }
+
+    /// Adds a hook run around every request; see `Middleware`.
+    pub fn middleware(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.middleware.push(middleware);
+        return self;
+    }
+
+    /// Sends requests through `transport` instead of a `reqwest` client.
+    /// The TLS, proxy, user agent, default header and pool settings only
+    /// apply to the default transport.
+    pub fn transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = Some(transport);
+        return self;
+    }
+
+    /// Masks the parameters matching `patterns` in this connection's logs
+    /// instead of those of the process-wide `redact::set_sensitive_patterns`.
+    pub fn sensitive_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.redactor = Some(Redactor::new(patterns));
+        return self;
+    }
+
+    /// Fetches file contents from the GenieACS file server at `url`. Without
+    /// this, the file server is assumed on port 7567 of the NBI host.
+    pub fn file_server(mut self, url: &str) -> Self {
+        self.file_server = Some(url.to_string());
+        return self;
+    }
+
+    /// Authenticates to the file server with HTTP basic auth. Without this
+    /// (or `file_server_bearer_auth`) file server requests carry no
+    /// credentials.
+    pub fn file_server_basic_auth(mut self, username: &str, password: Option<&str>) -> Self {
+        self.file_server_auth = Some(AcsAuth::Basic {
+            username: username.to_string(),
+            password: password.map(String::from),
+        });
+        return self;
+    }
+
+    pub fn file_server_bearer_auth(mut self, token: &str) -> Self {
+        self.file_server_auth = Some(AcsAuth::Bearer(token.to_string()));
+        return self;
+    }
+
+    fn build_client(&self) -> Result<Client, Box<dyn std::error::Error>> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.default_headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+
+        // The total timeout is set per request; streamed file transfers may
+        // run without one
+        let mut builder = Client::builder()
+            .timeout(None::<Duration>)
+            .connect_timeout(self.connect_timeout)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .tls_built_in_root_certs(self.builtin_root_certificates)
+            .default_headers(headers);
+
+        for pem in &self.root_certificates {
+            builder = builder.add_root_certificate(Certificate::from_pem(pem)?);
+        }
+        if let Some(pem) = &self.client_identity {
+            builder = builder.identity(Identity::from_pem(pem)?);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        return Ok(builder.build()?);
+    }
+
+    pub fn build(self) -> Result<AcsConnection, Box<dyn std::error::Error>> {
+        let transport: Arc<dyn HttpTransport> = match &self.transport {
+            Some(transport) => transport.clone(),
+            None => Arc::new(ReqwestTransport::new(self.build_client()?)),
+        };
+        let mut conn = AcsConnection::with_transport(self.acs_type, self.addr, transport);
+        conn.timeout = self.timeout;
+        conn.auth = self.auth;
+        conn.retry_policy = self.retry_policy;
+        conn.middleware = self.middleware;
+        conn.file_server = self.file_server;
+        conn.file_server_auth = self.file_server_auth;
+        conn.redactor = self.redactor;
+        return Ok(conn);
+    }
+}
@@ -1,28 +1,143 @@
 use std::collections::HashMap;
 
+use crate::util::path::*;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Root objects of the TR-181 (`Device`) and TR-098
+/// (`InternetGatewayDevice`) data models.
+pub const DATA_MODEL_ROOTS: &[&str] = &["Device", "InternetGatewayDevice"];
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 #[repr(C)]
 pub struct DataNode {
     pub value: String,
     pub value_type: String,
+    /// For parameters, whether the value can be set. For objects, whether
+    /// instances can be added or deleted (GenieACS `_writable` of the
+    /// object). Releases up to 0.2.28 left objects `false` regardless.
     pub writable: bool,
+    /// Value of `_timestamp`, i.e. when the ACS last refreshed this node
+    #[serde(default)]
+    pub timestamp: String,
     pub subnodes: HashMap<String, DataNode>,
 }
 
 unsafe impl Send for DataNode {}
 
+impl Default for DataNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DataNode {
     pub fn new() -> Self {
         Self {
             value: "".to_string(),
             value_type: "".to_string(),
             writable: false,
+            timestamp: "".to_string(),
             subnodes: HashMap::new(),
         }
     }
 
+    /// Builds a tree from a GenieACS device document (or part of one),
+    /// where parameters are objects with `_value`, `_type` and `_writable`.
+    /// Objects take `writable` from their own `_writable` too, so a
+    /// multi-instance object that accepts AddObject/DeleteObject is
+    /// writable; nodes without `_writable` are not.
+    pub fn from_genieacs_json(json: &Value) -> DataNode {
+        let mut root = DataNode::new();
+
+        if let Some(obj) = json.as_object() {
+            for (key, value) in obj {
+                let mut child_node = DataNode::new();
+
+                if let Some(sub_obj) = value.as_object() {
+                    if sub_obj.contains_key("_value") && sub_obj.contains_key("_type") {
+                        child_node.value = match &sub_obj["_value"] {
+                            Value::Bool(b) => b.to_string(),
+                            Value::String(s) => s.clone(),
+                            Value::Number(n) => n.to_string(),
+                            _ => "".to_string(),
+                        };
+                        child_node.value_type = sub_obj["_type"]
+                            .as_str()
+                            .map(String::from)
+                            .unwrap_or("".to_string());
+                        if sub_obj.contains_key("_writable") {
+                            child_node.writable = sub_obj
+                                .get("_writable")
+                                .and_then(|w| w.as_bool())
+                                .unwrap_or(false);
+                        } else {
+                            /* Should it be considered writable? */
+                            child_node.writable = false;
+                        }
+                    } else {
+                        child_node = DataNode::from_genieacs_json(value);
+                        // For objects `_writable` means instances can be added or deleted
+                        child_node.writable = sub_obj
+                            .get("_writable")
+                            .and_then(|w| w.as_bool())
+                            .unwrap_or(false);
+                    }
+                    child_node.timestamp = sub_obj
+                        .get("_timestamp")
+                        .and_then(|t| t.as_str())
+                        .map(String::from)
+                        .unwrap_or("".to_string());
+                }
+
+                root.subnodes.insert(key.clone(), child_node);
+            }
+        }
+
+        root
+    }
+
+    /// Inverse of `from_genieacs_json`: renders the subnodes of this node as
+    /// a GenieACS device document. Nodes with a type are parameters, all
+    /// others are objects.
+    pub fn to_genieacs_json(&self) -> Value {
+        let mut obj = Map::new();
+        for (name, node) in &self.subnodes {
+            let mut child = match node.value_type.is_empty() {
+                true => {
+                    let mut child = node.to_genieacs_json();
+                    child["_object"] = Value::Bool(true);
+                    child
+                }
+                false => {
+                    let value = match node.value_type.as_str() {
+                        "xsd:boolean" => node
+                            .value
+                            .parse::<bool>()
+                            .map(Value::Bool)
+                            .unwrap_or(Value::String(node.value.clone())),
+                        "xsd:int" | "xsd:unsignedInt" | "xsd:long" | "xsd:unsignedLong" => node
+                            .value
+                            .parse::<i64>()
+                            .map(|n| Value::Number(n.into()))
+                            .unwrap_or(Value::String(node.value.clone())),
+                        _ => Value::String(node.value.clone()),
+                    };
+                    let mut child = Map::new();
+                    child.insert("_value".to_string(), value);
+                    child.insert("_type".to_string(), Value::String(node.value_type.clone()));
+                    Value::Object(child)
+                }
+            };
+            child["_writable"] = Value::Bool(node.writable);
+            if !node.timestamp.is_empty() {
+                child["_timestamp"] = Value::String(node.timestamp.clone());
+            }
+            obj.insert(name.clone(), child);
+        }
+        return Value::Object(obj);
+    }
+
     pub fn get_subnode(&self, name: &str) -> Option<DataNode> {
         if self.subnodes.contains_key(name) {
             return Some(self.subnodes[name].clone());
@@ -30,9 +145,103 @@ impl DataNode {
         return None;
     }
 
+    /// Name of the data model root present below this node, `Device` or
+    /// `InternetGatewayDevice`.
+    pub fn data_model_root(&self) -> Option<&str> {
+        return DATA_MODEL_ROOTS
+            .iter()
+            .find(|root| self.subnodes.contains_key(**root))
+            .copied();
+    }
+
+    /// Looks up a node by its dot-separated path relative to this node,
+    /// e.g. `Device.DeviceInfo.SoftwareVersion`. A trailing dot is ignored.
+    pub fn get_node(&self, path: &str) -> Option<&DataNode> {
+        let mut node = self;
+        for name in path.trim_end_matches('.').split('.') {
+            if name.is_empty() {
+                continue;
+            }
+            node = node.subnodes.get(name)?;
+        }
+        return Some(node);
+    }
+
+    /// Replaces instance selectors in `path` with the instance numbers they
+    /// match in this tree, e.g. `Device.Hosts.Host.[MACAddress="aa:bb"].IPAddress`
+    /// becomes `Device.Hosts.Host.2.IPAddress`. Fails unless every selector
+    /// matches exactly one instance. Values are compared ignoring ASCII case,
+    /// as CPEs report e.g. MAC addresses in either case.
+    pub fn resolve_path(&self, path: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let mut node = Some(self);
+        let mut names: Vec<String> = Vec::new();
+
+        for segment in split_path(path)? {
+            match segment {
+                PathSegment::Name(name) => {
+                    node = node.and_then(|n| n.subnodes.get(&name));
+                    names.push(name);
+                }
+                PathSegment::Selector(keys) => {
+                    let object = match node {
+                        Some(object) => object,
+                        None => {
+                            return Err(Box::from(format!(
+                                "Object {} not found while resolving {}",
+                                names.join("."),
+                                path
+                            )))
+                        }
+                    };
+                    let matches: Vec<u32> = object
+                        .instance_numbers()
+                        .into_iter()
+                        .filter(|i| {
+                            let instance = &object.subnodes[&i.to_string()];
+                            keys.iter().all(|k| {
+                                instance
+                                    .get_node(&k.key)
+                                    .map(|n| n.value.eq_ignore_ascii_case(&k.value))
+                                    .unwrap_or(false)
+                            })
+                        })
+                        .collect();
+                    if matches.len() != 1 {
+                        return Err(Box::from(format!(
+                            "Selector in {} matches {} instances of {}",
+                            path,
+                            matches.len(),
+                            names.join(".")
+                        )));
+                    }
+                    node = object.subnodes.get(&matches[0].to_string());
+                    names.push(matches[0].to_string());
+                }
+            }
+        }
+
+        let mut resolved = names.join(".");
+        if path.ends_with('.') {
+            resolved.push('.');
+        }
+        return Ok(resolved);
+    }
+
+    /// Instance numbers of a multi-instance object, in ascending order.
+    pub fn instance_numbers(&self) -> Vec<u32> {
+        let mut instances: Vec<u32> = self
+            .subnodes
+            .keys()
+            .filter_map(|name| name.parse::<u32>().ok())
+            .collect();
+        instances.sort();
+        return instances;
+    }
+
     pub fn merge(&mut self, node: &DataNode) {
         self.value = node.value.clone();
         self.value_type = node.value_type.clone();
+        self.timestamp = node.timestamp.clone();
         for subnode in &node.subnodes {
             let idx = subnode.0.clone();
 
@@ -42,7 +251,7 @@ impl DataNode {
             }
 
             let new_node = self.subnodes.get_mut(&idx).unwrap();
-            new_node.merge(&subnode.1);
+            new_node.merge(subnode.1);
         }
     }
 }
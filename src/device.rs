@@ -1,10 +1,36 @@
 use crate::util::accessor::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 
 fn unset_vec_vec_str() -> Vec<Vec<String>> {
     Vec::new()
 }
 
+fn unset_u32() -> u32 {
+    0
+}
+
+/// Parameter values in tasks are typed JSON (`true`, `8080`); keep them as
+/// strings like the rest of the crate does.
+fn parameter_values_as_strings<'de, D>(deserializer: D) -> Result<Vec<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let values: Vec<Vec<Value>> = Deserialize::deserialize(deserializer)?;
+    Ok(values
+        .into_iter()
+        .map(|items| {
+            items
+                .into_iter()
+                .map(|item| match item {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                })
+                .collect()
+        })
+        .collect())
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 #[repr(C)]
 pub struct AcsDeviceId {
@@ -38,10 +64,40 @@ pub struct AcsTask {
     pub object_name: String,
 
     /// Populated for setParameterValues tasks: [[param, value], ...]
-    #[serde(default = "unset_vec_vec_str", rename = "parameterValues")]
+    #[serde(
+        default = "unset_vec_vec_str",
+        rename = "parameterValues",
+        deserialize_with = "parameter_values_as_strings"
+    )]
     pub parameter_values: Vec<Vec<String>>,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[repr(C)]
+pub struct AcsFault {
+    #[serde(default = "unset_str", rename = "_id")]
+    pub id: String,
+
+    #[serde(default = "unset_str")]
+    pub device: String,
+
+    #[serde(default = "unset_str")]
+    pub channel: String,
+
+    /// e.g. `cwmp.9005` for CPE faults or `script.ReferenceError`
+    #[serde(default = "unset_str")]
+    pub code: String,
+
+    #[serde(default = "unset_str")]
+    pub message: String,
+
+    #[serde(default = "unset_str")]
+    pub timestamp: String,
+
+    #[serde(default = "unset_u32")]
+    pub retries: u32,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 #[repr(C)]
 pub struct AcsDevice {
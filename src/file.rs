@@ -0,0 +1,307 @@
+use crate::util::accessor::*;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::Sha256;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn unset_u64() -> u64 {
+    0
+}
+
+fn unset_file_metadata() -> AcsFileMetadata {
+    AcsFileMetadata {
+        file_type: FileType::Other("".to_string()),
+        oui: "".to_string(),
+        product_class: "".to_string(),
+        version: "".to_string(),
+    }
+}
+
+/// File types of the TR-069 Download RPC.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum FileType {
+    FirmwareUpgradeImage,
+    WebContent,
+    VendorConfigurationFile,
+    ToneFile,
+    RingerFile,
+    /// Vendor-specific (`X <OUI> <name>`) or unknown types
+    Other(String),
+}
+
+impl FileType {
+    pub fn as_str(&self) -> &str {
+        return match self {
+            FileType::FirmwareUpgradeImage => "1 Firmware Upgrade Image",
+            FileType::WebContent => "2 Web Content",
+            FileType::VendorConfigurationFile => "3 Vendor Configuration File",
+            FileType::ToneFile => "4 Tone File",
+            FileType::RingerFile => "5 Ringer File",
+            FileType::Other(s) => s,
+        };
+    }
+}
+
+impl From<&str> for FileType {
+    fn from(s: &str) -> Self {
+        return match s {
+            "1 Firmware Upgrade Image" => FileType::FirmwareUpgradeImage,
+            "2 Web Content" => FileType::WebContent,
+            "3 Vendor Configuration File" => FileType::VendorConfigurationFile,
+            "4 Tone File" => FileType::ToneFile,
+            "5 Ringer File" => FileType::RingerFile,
+            _ => FileType::Other(s.to_string()),
+        };
+    }
+}
+
+impl std::fmt::Display for FileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for FileType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for FileType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        Ok(FileType::from(s.as_str()))
+    }
+}
+
+/// Metadata given when uploading a file, used to select files for devices.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[repr(C)]
+pub struct AcsFileMetadata {
+    #[serde(rename = "fileType")]
+    pub file_type: FileType,
+    #[serde(default = "unset_str")]
+    pub oui: String,
+    #[serde(default = "unset_str", rename = "productClass")]
+    pub product_class: String,
+    #[serde(default = "unset_str")]
+    pub version: String,
+}
+
+impl AcsFileMetadata {
+    pub fn new(file_type: FileType) -> Self {
+        return AcsFileMetadata {
+            file_type,
+            ..unset_file_metadata()
+        };
+    }
+
+    pub fn oui(mut self, oui: &str) -> Self {
+        self.oui = oui.to_string();
+        return self;
+    }
+
+    pub fn product_class(mut self, product_class: &str) -> Self {
+        self.product_class = product_class.to_string();
+        return self;
+    }
+
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        return self;
+    }
+}
+
+/// A file in the ACS file store, as listed under `/files`.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[repr(C)]
+pub struct AcsFile {
+    #[serde(default = "unset_str", rename = "_id")]
+    pub name: String,
+
+    /// Size in bytes
+    #[serde(default = "unset_u64")]
+    pub length: u64,
+
+    /// Hex MD5 digest, when the file store records one
+    #[serde(default = "unset_str")]
+    pub md5: String,
+
+    #[serde(default = "unset_str", rename = "uploadDate")]
+    pub upload_date: String,
+
+    #[serde(default = "unset_file_metadata")]
+    pub metadata: AcsFileMetadata,
+}
+
+/// Called with the bytes transferred so far and the total, if known.
+pub type ProgressFn = Arc<dyn Fn(u64, Option<u64>) + Send + Sync>;
+
+/// Options of `AcsConnection::upload` and `AcsConnection::fetch_file`.
+#[derive(Clone, Default)]
+pub struct TransferOptions {
+    pub progress: Option<ProgressFn>,
+    /// Hex digests the content must have. On mismatch the transfer fails:
+    /// an upload deletes the stored file unless `keep_unverified` is set,
+    /// `fetch_file_to_path` removes the partial file, and `fetch_file`
+    /// leaves what it wrote to the caller.
+    pub expected_md5: Option<String>,
+    pub expected_sha256: Option<String>,
+    /// Keep an uploaded file that failed verification in the file store,
+    /// e.g. to inspect it. Off by default, so that presets and
+    /// `latest_firmware` never pick up a corrupt image.
+    pub keep_unverified: bool,
+    /// Total time limit of the transfer. None by default, as large images
+    /// take long; connecting is still bounded by the connect timeout.
+    pub timeout: Option<Duration>,
+}
+
+impl TransferOptions {
+    pub fn progress(mut self, progress: impl Fn(u64, Option<u64>) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        return self;
+    }
+
+    pub fn expected_md5(mut self, md5: &str) -> Self {
+        self.expected_md5 = Some(md5.to_lowercase());
+        return self;
+    }
+
+    pub fn expected_sha256(mut self, sha256: &str) -> Self {
+        self.expected_sha256 = Some(sha256.to_lowercase());
+        return self;
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        return self;
+    }
+
+    pub fn keep_unverified(mut self, keep: bool) -> Self {
+        self.keep_unverified = keep;
+        return self;
+    }
+}
+
+/// Outcome of a completed upload or download.
+#[derive(Serialize, PartialEq, Clone, Debug)]
+pub struct TransferReport {
+    pub name: String,
+    pub length: u64,
+    /// Hex digests of the transferred content
+    pub md5: String,
+    pub sha256: String,
+}
+
+impl TransferReport {
+    /// Differences between this transfer and the `expected` digests and the
+    /// file as `stored` on the ACS, empty if everything matches.
+    pub(crate) fn mismatches(&self, expected: &TransferOptions, stored: &AcsFile) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(md5) = &expected.expected_md5 {
+            if *md5 != self.md5 {
+                problems.push(format!("MD5 {} does not match expected {}", self.md5, md5));
+            }
+        }
+        if let Some(sha256) = &expected.expected_sha256 {
+            if *sha256 != self.sha256 {
+                problems.push(format!(
+                    "SHA-256 {} does not match expected {}",
+                    self.sha256, sha256
+                ));
+            }
+        }
+        if stored.length != self.length {
+            problems.push(format!(
+                "{} bytes transferred, the stored file has {}",
+                self.length, stored.length
+            ));
+        }
+        if !stored.md5.is_empty() && stored.md5.to_lowercase() != self.md5 {
+            problems.push(format!(
+                "MD5 {} differs from the stored {}",
+                self.md5, stored.md5
+            ));
+        }
+        return problems;
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    return bytes.iter().map(|b| format!("{:02x}", b)).collect();
+}
+
+/// Running digests of a stream
+#[derive(Default)]
+pub(crate) struct Digests {
+    md5: Md5,
+    sha256: Sha256,
+    pub length: u64,
+}
+
+impl Digests {
+    pub fn update(&mut self, data: &[u8]) {
+        self.md5.update(data);
+        self.sha256.update(data);
+        self.length += data.len() as u64;
+    }
+
+    /// Hex MD5 and SHA-256 digests of the data so far.
+    pub fn finish(&self) -> (String, String) {
+        return (
+            to_hex(&self.md5.clone().finalize()),
+            to_hex(&self.sha256.clone().finalize()),
+        );
+    }
+}
+
+/// Reader computing digests of, and reporting progress on, what passes
+/// through it. The digests are shared so they can be read once the reader
+/// was consumed by the transport.
+pub(crate) struct HashingReader<R> {
+    inner: R,
+    digests: Arc<Mutex<Digests>>,
+    total: Option<u64>,
+    progress: Option<ProgressFn>,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R, total: Option<u64>, progress: Option<ProgressFn>) -> Self {
+        return HashingReader {
+            inner,
+            digests: Arc::new(Mutex::new(Digests::default())),
+            total,
+            progress,
+        };
+    }
+
+    pub fn digests(&self) -> Arc<Mutex<Digests>> {
+        return self.digests.clone();
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n == 0 {
+            return Ok(0);
+        }
+        let transferred = {
+            let mut digests = self.digests.lock().unwrap();
+            digests.update(&buf[..n]);
+            digests.length
+        };
+        if let Some(progress) = &self.progress {
+            progress(transferred, self.total);
+        }
+        return Ok(n);
+    }
+}
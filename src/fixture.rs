@@ -0,0 +1,325 @@
+//! Recording of real NBI sessions into fixture files and their replay
+//! without network, e.g. to reproduce field bugs in tests.
+
+use crate::redact::{redact_url, Redactor, REDACTED};
+use crate::transport::*;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE, SET_COOKIE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A body as stored in a fixture: text if it is UTF-8, base64 otherwise.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+pub struct FixtureBody {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub base64: String,
+}
+
+impl FixtureBody {
+    /// Stores `bytes`, masking sensitive parameter values of JSON bodies
+    /// and string literals next to sensitive names in other text bodies.
+    fn redacted(bytes: &[u8], redactor: &Redactor) -> Self {
+        return match std::str::from_utf8(bytes) {
+            Ok(text) => FixtureBody {
+                text: redactor.body(text),
+                base64: "".to_string(),
+            },
+            Err(_) => FixtureBody {
+                text: "".to_string(),
+                base64: STANDARD.encode(bytes),
+            },
+        };
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        if !self.base64.is_empty() {
+            return STANDARD.decode(&self.base64).unwrap_or_default();
+        }
+        return self.text.clone().into_bytes();
+    }
+}
+
+/// One recorded request and its outcome.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct FixtureExchange {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub request_headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub request_body: FixtureBody,
+    /// HTTP status; 0 when the request failed without a response
+    #[serde(default)]
+    pub status: u16,
+    #[serde(default)]
+    pub response_headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub response_body: FixtureBody,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<TransportErrorKind>,
+    pub elapsed_ms: u64,
+}
+
+/// Sequence of recorded exchanges, stored as JSON.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+pub struct Fixture {
+    pub exchanges: Vec<FixtureExchange>,
+}
+
+impl Fixture {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        return Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?);
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        return Ok(());
+    }
+}
+
+fn redacted_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    return headers
+        .iter()
+        .map(|(name, value)| {
+            let secret = value.is_sensitive()
+                || name == AUTHORIZATION
+                || name == COOKIE
+                || name == SET_COOKIE;
+            let value = if secret {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).to_string()
+            };
+            (name.as_str().to_string(), value)
+        })
+        .collect();
+}
+
+fn take_body(request: &mut HttpRequest) -> Result<Vec<u8>, TransportError> {
+    return match std::mem::replace(&mut request.body, HttpBody::Empty) {
+        HttpBody::Empty => Ok(Vec::new()),
+        HttpBody::Bytes(bytes) => Ok(bytes),
+        HttpBody::Reader(mut reader, _) => {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .map_err(|e| TransportError::new(TransportErrorKind::Other, e))?;
+            Ok(bytes)
+        }
+    };
+}
+
+/// Transport wrapper recording every exchange with `inner`, with secrets
+/// redacted. Streamed bodies are buffered in memory.
+pub struct RecordingTransport {
+    inner: Arc<dyn HttpTransport>,
+    fixture: Mutex<Fixture>,
+    redactor: Redactor,
+}
+
+impl RecordingTransport {
+    /// Records with the process-wide sensitive patterns as of now.
+    pub fn new(inner: Arc<dyn HttpTransport>) -> Self {
+        return RecordingTransport {
+            inner,
+            fixture: Mutex::new(Fixture::default()),
+            redactor: Redactor::global(),
+        };
+    }
+
+    pub fn redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        return self;
+    }
+
+    /// Exchanges recorded so far.
+    pub fn fixture(&self) -> Fixture {
+        return self.fixture.lock().unwrap().clone();
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        return self.fixture().save(path);
+    }
+}
+
+impl HttpTransport for RecordingTransport {
+    fn execute(&self, mut request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let body = take_body(&mut request)?;
+        let mut exchange = FixtureExchange {
+            method: request.method.to_string(),
+            url: redact_url(&request.url),
+            request_headers: redacted_headers(&request.headers),
+            request_body: FixtureBody::redacted(&body, &self.redactor),
+            status: 0,
+            response_headers: BTreeMap::new(),
+            response_body: FixtureBody::default(),
+            error: None,
+            error_kind: None,
+            elapsed_ms: 0,
+        };
+        request.body = HttpBody::Bytes(body);
+
+        let started = Instant::now();
+        let result = self.inner.execute(request).and_then(|response| {
+            let status = response.status;
+            let headers = response.headers.clone();
+            let bytes = response
+                .bytes()
+                .map_err(|e| TransportError::new(TransportErrorKind::Other, e))?;
+            return Ok((status, headers, bytes));
+        });
+        exchange.elapsed_ms = started.elapsed().as_millis() as u64;
+
+        let result = match result {
+            Ok((status, headers, bytes)) => {
+                exchange.status = status.as_u16();
+                exchange.response_headers = redacted_headers(&headers);
+                exchange.response_body = FixtureBody::redacted(&bytes, &self.redactor);
+                let mut response = HttpResponse::from_bytes(status, bytes);
+                response.headers = headers;
+                Ok(response)
+            }
+            Err(err) => {
+                exchange.error = Some(err.to_string());
+                exchange.error_kind = Some(err.kind);
+                Err(err)
+            }
+        };
+
+        self.fixture.lock().unwrap().exchanges.push(exchange);
+        return result;
+    }
+}
+
+/// Transport answering from a recorded `Fixture`, in order. Any request
+/// that differs from the recorded one (method, URL or body, compared after
+/// redaction) fails with an error describing the divergence, as does a
+/// request past the end of the fixture.
+pub struct ReplayTransport {
+    fixture: Fixture,
+    position: Mutex<usize>,
+    replay_timings: bool,
+    redactor: Redactor,
+}
+
+impl ReplayTransport {
+    pub fn new(fixture: Fixture) -> Self {
+        return ReplayTransport {
+            fixture,
+            position: Mutex::new(0),
+            replay_timings: false,
+            redactor: Redactor::global(),
+        };
+    }
+
+    /// Patterns the fixture was recorded with, to redact requests the same
+    /// way before comparing them.
+    pub fn redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        return self;
+    }
+
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        return Ok(ReplayTransport::new(Fixture::load(path)?));
+    }
+
+    /// Whether to wait as long as the recorded request took.
+    pub fn replay_timings(mut self, enabled: bool) -> Self {
+        self.replay_timings = enabled;
+        return self;
+    }
+
+    /// Number of exchanges not replayed yet.
+    pub fn remaining(&self) -> usize {
+        return self.fixture.exchanges.len() - *self.position.lock().unwrap();
+    }
+
+    /// Fails unless every recorded exchange has been replayed.
+    pub fn finish(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let remaining = self.remaining();
+        if remaining > 0 {
+            return Err(Box::from(format!(
+                "Replay finished with {} recorded request(s) not sent, next: {} {}",
+                remaining,
+                self.fixture.exchanges[self.fixture.exchanges.len() - remaining].method,
+                self.fixture.exchanges[self.fixture.exchanges.len() - remaining].url
+            )));
+        }
+        return Ok(());
+    }
+}
+
+impl HttpTransport for ReplayTransport {
+    fn execute(&self, mut request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let body = FixtureBody::redacted(&take_body(&mut request)?, &self.redactor);
+        let method = request.method.to_string();
+        let url = redact_url(&request.url);
+
+        let mut position = self.position.lock().unwrap();
+        let diverged = |reason: String| {
+            TransportError::new(
+                TransportErrorKind::Other,
+                format!("Replay diverged at request #{}: {}", *position + 1, reason),
+            )
+        };
+        let exchange = match self.fixture.exchanges.get(*position) {
+            Some(exchange) => exchange,
+            None => {
+                return Err(diverged(format!(
+                    "unexpected {} {} past the end of the fixture",
+                    method, url
+                )))
+            }
+        };
+        if exchange.method != method || exchange.url != url {
+            return Err(diverged(format!(
+                "expected {} {}, got {} {}",
+                exchange.method, exchange.url, method, url
+            )));
+        }
+        if exchange.request_body != body {
+            return Err(diverged(format!(
+                "{} {} sent a different body: expected {:?}, got {:?}",
+                method, url, exchange.request_body.text, body.text
+            )));
+        }
+        *position += 1;
+        drop(position);
+
+        if self.replay_timings {
+            std::thread::sleep(Duration::from_millis(exchange.elapsed_ms));
+        }
+
+        if let Some(error) = &exchange.error {
+            return Err(TransportError::new(
+                exchange.error_kind.unwrap_or(TransportErrorKind::Other),
+                error.clone(),
+            ));
+        }
+
+        let status = StatusCode::from_u16(exchange.status).unwrap_or(StatusCode::OK);
+        let mut response = HttpResponse::from_bytes(status, exchange.response_body.bytes());
+        for (name, value) in &exchange.response_headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                response.headers.insert(name, value);
+            }
+        }
+        // Keep the header consistent with the (possibly redacted) body
+        response.headers.insert(
+            reqwest::header::CONTENT_LENGTH,
+            HeaderValue::from(exchange.response_body.bytes().len()),
+        );
+        return Ok(response);
+    }
+}
@@ -1,7 +1,21 @@
 pub mod acs_type;
+pub mod bulk;
+pub mod config_snapshot;
 pub mod connection;
+pub mod connection_builder;
 pub mod data_node;
 pub mod device;
+pub mod file;
+pub mod fixture;
+pub mod middleware;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod parameter_value;
+pub mod preset;
+pub mod provision;
+pub mod redact;
 pub mod request;
+pub mod retry;
+pub mod transport;
 pub mod util;
+pub mod virtual_parameter;
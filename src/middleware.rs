@@ -0,0 +1,28 @@
+use crate::transport::{HttpRequest, HttpResponse, TransportError};
+use reqwest::Method;
+use std::time::Duration;
+
+/// Hook around every HTTP request `AcsConnection` sends to the NBI, e.g. to
+/// add correlation headers, record metrics or audit mutations.
+///
+/// Middlewares run in the order they were added. `on_request` is called
+/// before every attempt (including retries) and may modify the request;
+/// returning an error aborts the operation without sending it.
+pub trait Middleware: Send + Sync {
+    fn on_request(&self, _request: &mut HttpRequest) -> Result<(), Box<dyn std::error::Error>> {
+        return Ok(());
+    }
+
+    /// Called when a response has been received, before its body is read.
+    fn on_response(
+        &self,
+        _method: &Method,
+        _url: &str,
+        _response: &HttpResponse,
+        _elapsed: Duration,
+    ) {
+    }
+
+    /// Called when a request failed without a response.
+    fn on_error(&self, _method: &Method, _url: &str, _error: &TransportError, _elapsed: Duration) {}
+}
@@ -0,0 +1,720 @@
+//! In-memory mock of the GenieACS NBI, for testing code that uses
+//! `AcsConnection` without a real ACS. Enabled by the `mock` feature.
+//!
+//! `MockNbi` can be used in-process as an `HttpTransport` (see
+//! `MockNbi::connection`) or served over HTTP with `MockNbi::serve`. As in
+//! GenieACS, file contents are only served by the file server: requests to
+//! port 7567 in-process, or to `MockServer::file_server_addr` over HTTP.
+//! Devices with a `SimulatedCpe` attached execute their tasks.
+
+pub mod query;
+pub mod server;
+pub mod simulator;
+
+use crate::acs_type::AcsType;
+use crate::connection::{AcsConnection, DEFAULT_FILE_SERVER_PORT};
+use crate::transport::*;
+use crate::util::timestamp::format_timestamp;
+use md5::Digest;
+use query::{matches, project};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use simulator::SimulatedCpe;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Address used by connections created with `MockNbi::connection`
+pub const MOCK_ADDR: &str = "http://mock-nbi";
+
+/// File server address those connections derive from `MOCK_ADDR`
+pub const MOCK_FILE_SERVER_ADDR: &str = "http://mock-nbi:7567";
+
+/// Collections stored as plain JSON documents keyed by `_id`
+const COLLECTIONS: &[&str] = &["presets", "provisions", "virtual_parameters", "config"];
+
+/// Collections the NBI can write; `config` is only writable through the
+/// GenieACS UI, so the mock seeds it with `put_object`
+const WRITABLE_COLLECTIONS: &[&str] = &["presets", "provisions", "virtual_parameters"];
+
+/// Scripted behaviour for requests whose path starts with `path_prefix`
+/// (e.g. `/devices`): answer with `status` instead of handling the request
+/// and/or wait `delay` first.
+#[derive(PartialEq, Clone, Debug)]
+pub struct MockRule {
+    pub method: Option<Method>,
+    pub path_prefix: String,
+    pub status: Option<u16>,
+    pub body: String,
+    pub delay: Duration,
+    /// Number of requests the rule applies to; `None` means forever
+    pub remaining: Option<usize>,
+}
+
+impl MockRule {
+    /// Answers matching requests with `status`.
+    pub fn fail(path_prefix: &str, status: u16) -> Self {
+        return MockRule {
+            method: None,
+            path_prefix: path_prefix.to_string(),
+            status: Some(status),
+            body: "".to_string(),
+            delay: Duration::ZERO,
+            remaining: None,
+        };
+    }
+
+    /// Delays matching requests by `delay`, then handles them normally.
+    pub fn delay(path_prefix: &str, delay: Duration) -> Self {
+        return MockRule {
+            method: None,
+            path_prefix: path_prefix.to_string(),
+            status: None,
+            body: "".to_string(),
+            delay,
+            remaining: None,
+        };
+    }
+
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = Some(method);
+        return self;
+    }
+
+    pub fn times(mut self, times: usize) -> Self {
+        self.remaining = Some(times);
+        return self;
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.body = body.to_string();
+        return self;
+    }
+}
+
+/// A request received by the mock, for assertions.
+#[derive(PartialEq, Clone, Debug)]
+pub struct MockRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+struct MockFile {
+    metadata: Value,
+    content: Vec<u8>,
+}
+
+#[derive(Default)]
+struct MockState {
+    devices: BTreeMap<String, Value>,
+    online: BTreeMap<String, bool>,
+    cpes: BTreeMap<String, SimulatedCpe>,
+    tasks: Vec<Value>,
+    faults: Vec<Value>,
+    files: BTreeMap<String, MockFile>,
+    collections: BTreeMap<String, BTreeMap<String, Value>>,
+    rules: Vec<MockRule>,
+    requests: Vec<MockRequest>,
+    next_task_id: u64,
+}
+
+/// In-memory GenieACS NBI holding devices, tasks, faults, files, tags,
+/// presets, provisions, virtual parameters and config. Clones share state.
+#[derive(Clone, Default)]
+pub struct MockNbi {
+    state: Arc<Mutex<MockState>>,
+}
+
+fn now() -> String {
+    return format_timestamp(SystemTime::now());
+}
+
+fn fault_doc(device_id: &str, code: &str, message: &str) -> Value {
+    return json!({
+        "_id": format!("{}:default", device_id),
+        "device": device_id,
+        "channel": "default",
+        "code": code,
+        "message": message,
+        "detail": null,
+        "timestamp": now(),
+        "retries": 0,
+    });
+}
+
+fn json_response(status: StatusCode, value: &Value) -> HttpResponse {
+    let mut response = HttpResponse::from_bytes(status, value.to_string().into_bytes());
+    response
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    return response;
+}
+
+fn empty_response(status: StatusCode) -> HttpResponse {
+    return HttpResponse::from_bytes(status, Vec::new());
+}
+
+fn header_str(headers: &HeaderMap, name: &str) -> String {
+    return headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+}
+
+impl MockNbi {
+    pub fn new() -> Self {
+        return MockNbi::default();
+    }
+
+    /// Connection to this mock using it as in-process transport.
+    pub fn connection(&self) -> AcsConnection {
+        return AcsConnection::with_transport(
+            AcsType::GenieAcs,
+            MOCK_ADDR.to_string(),
+            Arc::new(self.clone()),
+        );
+    }
+
+    /// Adds (or replaces) a device from its GenieACS JSON document. Without
+    /// an `_id`, one is derived from `_deviceId` as `OUI-ProductClass-Serial`.
+    /// New devices are online.
+    pub fn add_device(&self, mut doc: Value) -> Result<String, Box<dyn std::error::Error>> {
+        let obj = doc
+            .as_object_mut()
+            .ok_or("Device document must be an object")?;
+        let id = match obj.get("_id").and_then(|v| v.as_str()) {
+            Some(id) => id.to_string(),
+            None => {
+                let device_id = obj
+                    .get("_deviceId")
+                    .ok_or("Device has no _id or _deviceId")?;
+                let field = |name: &str| {
+                    device_id
+                        .get(name)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string()
+                };
+                format!(
+                    "{}-{}-{}",
+                    field("_OUI"),
+                    field("_ProductClass"),
+                    field("_SerialNumber")
+                )
+            }
+        };
+        obj.insert("_id".to_string(), Value::String(id.clone()));
+        obj.entry("_registered")
+            .or_insert_with(|| Value::String(now()));
+        obj.entry("_lastInform")
+            .or_insert_with(|| Value::String(now()));
+
+        let mut state = self.state.lock().unwrap();
+        state.devices.insert(id.clone(), doc);
+        state.online.insert(id.clone(), true);
+        return Ok(id);
+    }
+
+    /// Seeds devices from a JSON array of device documents, e.g. the output
+    /// of `GET /devices` on a real ACS.
+    pub fn load_devices(&self, json: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let docs: Vec<Value> = serde_json::from_str(json)?;
+        return docs.into_iter().map(|doc| self.add_device(doc)).collect();
+    }
+
+    pub fn device(&self, id: &str) -> Option<Value> {
+        return self.state.lock().unwrap().devices.get(id).cloned();
+    }
+
+    /// Offline devices do not answer connection requests, so their tasks
+    /// stay queued and the NBI answers 202.
+    pub fn set_online(&self, id: &str, online: bool) {
+        self.state
+            .lock()
+            .unwrap()
+            .online
+            .insert(id.to_string(), online);
+    }
+
+    /// Attaches a simulated CPE to a device: from now on the device's
+    /// parameters mirror the CPE's data model, and tasks are executed by the
+    /// CPE when the device is reached by a connection request.
+    pub fn attach_cpe(
+        &self,
+        device_id: &str,
+        cpe: SimulatedCpe,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut state = self.state.lock().unwrap();
+        let device = state
+            .devices
+            .get_mut(device_id)
+            .ok_or_else(|| format!("Device {} not found", device_id))?;
+        sync_device(device, &cpe);
+        state.cpes.insert(device_id.to_string(), cpe);
+        return Ok(());
+    }
+
+    /// Current state of the CPE attached to a device.
+    pub fn cpe(&self, device_id: &str) -> Option<SimulatedCpe> {
+        return self.state.lock().unwrap().cpes.get(device_id).cloned();
+    }
+
+    pub fn tasks(&self) -> Vec<Value> {
+        return self.state.lock().unwrap().tasks.clone();
+    }
+
+    pub fn faults(&self) -> Vec<Value> {
+        return self.state.lock().unwrap().faults.clone();
+    }
+
+    pub fn add_fault(&self, device_id: &str, code: &str, message: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .faults
+            .push(fault_doc(device_id, code, message));
+    }
+
+    /// Stores a file as if uploaded with `PUT /files/<name>`.
+    pub fn add_file(&self, name: &str, metadata: Value, content: Vec<u8>) {
+        let doc = json!({
+            "_id": name,
+            "filename": name,
+            "length": content.len(),
+            "md5": crate::file::to_hex(&md5::Md5::digest(&content)),
+            "uploadDate": now(),
+            "metadata": metadata,
+        });
+        self.state.lock().unwrap().files.insert(
+            name.to_string(),
+            MockFile {
+                metadata: doc,
+                content,
+            },
+        );
+    }
+
+    pub fn files(&self) -> Vec<Value> {
+        return self
+            .state
+            .lock()
+            .unwrap()
+            .files
+            .values()
+            .map(|f| f.metadata.clone())
+            .collect();
+    }
+
+    pub fn file_content(&self, name: &str) -> Option<Vec<u8>> {
+        return self
+            .state
+            .lock()
+            .unwrap()
+            .files
+            .get(name)
+            .map(|f| f.content.clone());
+    }
+
+    /// Stores a document in one of the `presets`, `provisions`,
+    /// `virtual_parameters` or `config` collections.
+    pub fn put_object(&self, collection: &str, id: &str, mut doc: Value) {
+        if let Some(obj) = doc.as_object_mut() {
+            obj.insert("_id".to_string(), Value::String(id.to_string()));
+        }
+        self.state
+            .lock()
+            .unwrap()
+            .collections
+            .entry(collection.to_string())
+            .or_default()
+            .insert(id.to_string(), doc);
+    }
+
+    pub fn object(&self, collection: &str, id: &str) -> Option<Value> {
+        return self
+            .state
+            .lock()
+            .unwrap()
+            .collections
+            .get(collection)
+            .and_then(|c| c.get(id))
+            .cloned();
+    }
+
+    pub fn add_rule(&self, rule: MockRule) {
+        self.state.lock().unwrap().rules.push(rule);
+    }
+
+    /// Requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        return self.state.lock().unwrap().requests.clone();
+    }
+
+    /// Applies the first matching scripted rule. Returns the scripted
+    /// response, if the rule replaces the normal handling.
+    fn apply_rules(&self, method: &Method, path: &str) -> Option<HttpResponse> {
+        let (delay, response) = {
+            let mut state = self.state.lock().unwrap();
+            let rule = state.rules.iter_mut().find(|r| {
+                r.remaining != Some(0)
+                    && path.starts_with(&r.path_prefix)
+                    && r.method.as_ref().map(|m| m == method).unwrap_or(true)
+            })?;
+            if let Some(remaining) = rule.remaining.as_mut() {
+                *remaining -= 1;
+            }
+            let response = rule.status.map(|status| {
+                HttpResponse::from_bytes(
+                    StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                    rule.body.clone().into_bytes(),
+                )
+            });
+            (rule.delay, response)
+        };
+        std::thread::sleep(delay);
+        return response;
+    }
+
+    /// Handles one request: as the file server if `url` is on port 7567,
+    /// else as the NBI. Relative URLs go to the NBI.
+    pub fn handle(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+    ) -> HttpResponse {
+        let file_server = reqwest::Url::parse(url)
+            .map(|url| url.port() == Some(DEFAULT_FILE_SERVER_PORT))
+            .unwrap_or(false);
+        return self.handle_at(file_server, method, url, headers, body);
+    }
+
+    /// Handles one request to the file server, or else to the NBI.
+    fn handle_at(
+        &self,
+        file_server: bool,
+        method: Method,
+        url: &str,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+    ) -> HttpResponse {
+        let parsed = match reqwest::Url::parse(url)
+            .or_else(|_| reqwest::Url::parse(&format!("{}{}", MOCK_ADDR, url)))
+        {
+            Ok(parsed) => parsed,
+            Err(_) => return empty_response(StatusCode::BAD_REQUEST),
+        };
+        let segments: Vec<String> = parsed
+            .path_segments()
+            .map(|s| {
+                s.filter(|s| !s.is_empty())
+                    .map(|s| {
+                        urlencoding::decode(s)
+                            .map(|s| s.to_string())
+                            .unwrap_or(s.to_string())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let query: Vec<(String, String)> = parsed
+            .query_pairs()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        self.state.lock().unwrap().requests.push(MockRequest {
+            method: method.clone(),
+            path: parsed.path().to_string(),
+            query: query.clone(),
+            body: body.clone(),
+        });
+
+        if let Some(response) = self.apply_rules(&method, parsed.path()) {
+            return response;
+        }
+
+        let param = |name: &str| {
+            query
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.clone())
+        };
+        let filter = match param("query") {
+            Some(q) => match serde_json::from_str::<Value>(&q) {
+                Ok(q) => q,
+                Err(_) => return empty_response(StatusCode::BAD_REQUEST),
+            },
+            None => json!({}),
+        };
+        let projection = param("projection").unwrap_or_default();
+        let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+
+        if file_server {
+            return match (method.as_str(), segments.as_slice()) {
+                ("GET", [name]) => match self.file_content(name) {
+                    Some(content) => HttpResponse::from_bytes(StatusCode::OK, content),
+                    None => empty_response(StatusCode::NOT_FOUND),
+                },
+                ("GET", _) => empty_response(StatusCode::NOT_FOUND),
+                _ => empty_response(StatusCode::METHOD_NOT_ALLOWED),
+            };
+        }
+
+        return match (method.as_str(), segments.as_slice()) {
+            ("GET", ["devices"]) => {
+                let state = self.state.lock().unwrap();
+                let docs: Vec<Value> = state
+                    .devices
+                    .values()
+                    .filter(|d| matches(d, &filter))
+                    .map(|d| project(d, &projection))
+                    .collect();
+                json_response(StatusCode::OK, &Value::Array(docs))
+            }
+            ("DELETE", ["devices", id]) => {
+                let mut state = self.state.lock().unwrap();
+                if state.devices.remove(*id).is_none() {
+                    return empty_response(StatusCode::NOT_FOUND);
+                }
+                state.tasks.retain(|t| t["device"] != *id);
+                state.faults.retain(|f| f["device"] != *id);
+                empty_response(StatusCode::OK)
+            }
+            ("POST", ["devices", id, "tasks"]) => {
+                let connection_request = param("connection_request").is_some();
+                self.post_task(id, &body, connection_request)
+            }
+            ("POST", ["devices", id, "tags", tag]) | ("DELETE", ["devices", id, "tags", tag]) => {
+                let mut state = self.state.lock().unwrap();
+                let device = match state.devices.get_mut(*id) {
+                    Some(device) => device,
+                    None => return empty_response(StatusCode::NOT_FOUND),
+                };
+                let tags = device
+                    .as_object_mut()
+                    .unwrap()
+                    .entry("_tags")
+                    .or_insert_with(|| json!([]));
+                if let Some(tags) = tags.as_array_mut() {
+                    tags.retain(|t| t != *tag);
+                    if method == Method::POST {
+                        tags.push(Value::String(tag.to_string()));
+                    }
+                }
+                empty_response(StatusCode::OK)
+            }
+            ("GET", ["tasks"]) => {
+                let state = self.state.lock().unwrap();
+                let docs: Vec<Value> = state
+                    .tasks
+                    .iter()
+                    .filter(|t| matches(t, &filter))
+                    .map(|t| project(t, &projection))
+                    .collect();
+                json_response(StatusCode::OK, &Value::Array(docs))
+            }
+            ("DELETE", ["tasks", id]) => {
+                let mut state = self.state.lock().unwrap();
+                let before = state.tasks.len();
+                state.tasks.retain(|t| t["_id"] != *id);
+                if state.tasks.len() == before {
+                    return empty_response(StatusCode::NOT_FOUND);
+                }
+                empty_response(StatusCode::OK)
+            }
+            ("GET", ["faults"]) => {
+                let state = self.state.lock().unwrap();
+                let docs: Vec<Value> = state
+                    .faults
+                    .iter()
+                    .filter(|f| matches(f, &filter))
+                    .map(|f| project(f, &projection))
+                    .collect();
+                json_response(StatusCode::OK, &Value::Array(docs))
+            }
+            ("DELETE", ["faults", id]) => {
+                let mut state = self.state.lock().unwrap();
+                state.faults.retain(|f| f["_id"] != *id);
+                empty_response(StatusCode::OK)
+            }
+            ("GET", ["files"]) => {
+                let state = self.state.lock().unwrap();
+                let docs: Vec<Value> = state
+                    .files
+                    .values()
+                    .filter(|f| matches(&f.metadata, &filter))
+                    .map(|f| project(&f.metadata, &projection))
+                    .collect();
+                json_response(StatusCode::OK, &Value::Array(docs))
+            }
+            ("PUT", ["files", name]) => {
+                let metadata = json!({
+                    "fileType": header_str(headers, "fileType"),
+                    "oui": header_str(headers, "oui"),
+                    "productClass": header_str(headers, "productClass"),
+                    "version": header_str(headers, "version"),
+                });
+                self.add_file(name, metadata, body);
+                empty_response(StatusCode::CREATED)
+            }
+            // File contents are only served by the file server
+            ("GET", ["files", _]) => empty_response(StatusCode::METHOD_NOT_ALLOWED),
+            ("DELETE", ["files", name]) => {
+                let mut state = self.state.lock().unwrap();
+                if state.files.remove(*name).is_none() {
+                    return empty_response(StatusCode::NOT_FOUND);
+                }
+                empty_response(StatusCode::OK)
+            }
+            ("GET", [collection]) if COLLECTIONS.contains(collection) => {
+                let state = self.state.lock().unwrap();
+                let docs: Vec<Value> = state
+                    .collections
+                    .get(*collection)
+                    .map(|c| {
+                        c.values()
+                            .filter(|d| matches(d, &filter))
+                            .map(|d| project(d, &projection))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                json_response(StatusCode::OK, &Value::Array(docs))
+            }
+            ("PUT", [collection, id]) if WRITABLE_COLLECTIONS.contains(collection) => {
+                // Provisions and virtual parameters are uploaded as raw scripts
+                let doc = match serde_json::from_slice::<Value>(&body) {
+                    Ok(doc) if doc.is_object() => doc,
+                    _ => json!({ "script": String::from_utf8_lossy(&body) }),
+                };
+                self.put_object(collection, id, doc);
+                empty_response(StatusCode::OK)
+            }
+            ("DELETE", [collection, id]) if WRITABLE_COLLECTIONS.contains(collection) => {
+                let mut state = self.state.lock().unwrap();
+                let removed = state
+                    .collections
+                    .get_mut(*collection)
+                    .and_then(|c| c.remove(*id));
+                if removed.is_none() {
+                    return empty_response(StatusCode::NOT_FOUND);
+                }
+                empty_response(StatusCode::OK)
+            }
+            _ => empty_response(StatusCode::NOT_FOUND),
+        };
+    }
+
+    /// Queues a task. Online devices reached by a connection request
+    /// complete it right away (200); otherwise it stays queued (202).
+    fn post_task(&self, device_id: &str, body: &[u8], connection_request: bool) -> HttpResponse {
+        let mut task: Value = match serde_json::from_slice(body) {
+            Ok(task @ Value::Object(_)) => task,
+            _ => return empty_response(StatusCode::BAD_REQUEST),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if !state.devices.contains_key(device_id) {
+            return empty_response(StatusCode::NOT_FOUND);
+        }
+        state.next_task_id += 1;
+        let obj = task.as_object_mut().unwrap();
+        obj.insert(
+            "_id".to_string(),
+            Value::String(format!("{:024x}", state.next_task_id)),
+        );
+        obj.insert("device".to_string(), Value::String(device_id.to_string()));
+        obj.insert("timestamp".to_string(), Value::String(now()));
+
+        let online = state.online.get(device_id).copied().unwrap_or(false);
+        if connection_request && online {
+            if let Some(device) = state.devices.get_mut(device_id) {
+                device["_lastInform"] = Value::String(now());
+            }
+            if !state.cpes.contains_key(device_id) {
+                return json_response(StatusCode::OK, &task);
+            }
+            state.tasks.push(task.clone());
+            if run_cpe_session(&mut state, device_id) {
+                return json_response(StatusCode::OK, &task);
+            }
+            return json_response(StatusCode::ACCEPTED, &task);
+        }
+
+        state.tasks.push(task.clone());
+        return json_response(StatusCode::ACCEPTED, &task);
+    }
+}
+
+/// Replaces the parameters of a device document with the CPE's model.
+fn sync_device(device: &mut Value, cpe: &SimulatedCpe) {
+    if let (Some(device), Value::Object(params)) = (device.as_object_mut(), cpe.to_device_doc()) {
+        device.retain(|key, _| key.starts_with('_'));
+        device.extend(params);
+    }
+}
+
+/// Executes the queued tasks of a device on its CPE, oldest first, as in a
+/// CWMP session. A fault stops the session and leaves the faulted task and
+/// those after it queued. Returns whether all tasks completed.
+fn run_cpe_session(state: &mut MockState, device_id: &str) -> bool {
+    let MockState {
+        devices,
+        cpes,
+        tasks,
+        faults,
+        files,
+        ..
+    } = state;
+    let cpe = match cpes.get_mut(device_id) {
+        Some(cpe) => cpe,
+        None => return false,
+    };
+    let lookup = |name: &str| files.get(name).map(|f| f.metadata.clone());
+
+    let mut completed = true;
+    let mut remaining = Vec::new();
+    for task in std::mem::take(tasks) {
+        if !completed || task["device"] != device_id {
+            remaining.push(task);
+            continue;
+        }
+        if let Err(fault) = cpe.apply_task(&task, &lookup) {
+            faults.retain(|f| f["device"] != device_id);
+            faults.push(fault_doc(
+                device_id,
+                &format!("cwmp.{}", fault.code),
+                &fault.message,
+            ));
+            completed = false;
+            remaining.push(task);
+        }
+    }
+    *tasks = remaining;
+
+    if let Some(device) = devices.get_mut(device_id) {
+        sync_device(device, cpe);
+    }
+    return completed;
+}
+
+impl HttpTransport for MockNbi {
+    fn execute(&self, mut request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let body = match std::mem::replace(&mut request.body, HttpBody::Empty) {
+            HttpBody::Empty => Vec::new(),
+            HttpBody::Bytes(bytes) => bytes,
+            HttpBody::Reader(mut reader, _) => {
+                let mut bytes = Vec::new();
+                reader
+                    .read_to_end(&mut bytes)
+                    .map_err(|e| TransportError::new(TransportErrorKind::Other, e))?;
+                bytes
+            }
+        };
+        return Ok(self.handle(request.method, &request.url, &request.headers, body));
+    }
+}
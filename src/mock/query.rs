@@ -0,0 +1,173 @@
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+/// Looks up a dotted path in a JSON document.
+pub fn lookup<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut value = doc;
+    for name in path.split('.').filter(|n| !n.is_empty()) {
+        value = value.as_object()?.get(name)?;
+    }
+    return Some(value);
+}
+
+/// Parameters are stored as `{"_value": ..}`; queries compare the value.
+fn scalar(value: &Value) -> &Value {
+    return match value.as_object().and_then(|o| o.get("_value")) {
+        Some(inner) => inner,
+        None => value,
+    };
+}
+
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    return match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+}
+
+fn equals(value: Option<&Value>, expected: &Value) -> bool {
+    let value = match value {
+        Some(value) => scalar(value),
+        None => return expected.is_null(),
+    };
+    if let Value::Array(items) = value {
+        // Arrays such as `_tags` match if any element does
+        if !expected.is_array() {
+            return items.iter().any(|item| item == expected);
+        }
+    }
+    return value == expected || compare(value, expected) == Some(Ordering::Equal);
+}
+
+/// Supports `^` and `$` anchors around a literal; enough for the usual
+/// prefix/suffix/substring device searches.
+fn regex_match(value: Option<&Value>, pattern: &str) -> bool {
+    let text = match value.map(scalar) {
+        Some(Value::String(text)) => text,
+        _ => return false,
+    };
+    let (anchored_start, pattern) = match pattern.strip_prefix('^') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let (anchored_end, pattern) = match pattern.strip_suffix('$') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    return match (anchored_start, anchored_end) {
+        (true, true) => text == pattern,
+        (true, false) => text.starts_with(pattern),
+        (false, true) => text.ends_with(pattern),
+        (false, false) => text.contains(pattern),
+    };
+}
+
+fn matches_condition(value: Option<&Value>, condition: &Value) -> bool {
+    let operators = match condition.as_object() {
+        Some(obj) if obj.keys().all(|k| k.starts_with('$')) && !obj.is_empty() => obj,
+        _ => return equals(value, condition),
+    };
+
+    for (operator, operand) in operators {
+        let ok = match operator.as_str() {
+            "$eq" => equals(value, operand),
+            "$ne" => !equals(value, operand),
+            "$gt" | "$gte" | "$lt" | "$lte" => {
+                match value.and_then(|v| compare(scalar(v), operand)) {
+                    Some(ordering) => match operator.as_str() {
+                        "$gt" => ordering == Ordering::Greater,
+                        "$gte" => ordering != Ordering::Less,
+                        "$lt" => ordering == Ordering::Less,
+                        _ => ordering != Ordering::Greater,
+                    },
+                    None => false,
+                }
+            }
+            "$in" => operand
+                .as_array()
+                .map(|items| items.iter().any(|item| equals(value, item)))
+                .unwrap_or(false),
+            "$nin" => operand
+                .as_array()
+                .map(|items| !items.iter().any(|item| equals(value, item)))
+                .unwrap_or(true),
+            "$exists" => value.is_some() == operand.as_bool().unwrap_or(true),
+            "$regex" => regex_match(value, operand.as_str().unwrap_or("")),
+            "$options" => true,
+            _ => false,
+        };
+        if !ok {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+/// Whether `doc` matches a MongoDB-style `query` as accepted by the
+/// GenieACS NBI. Supports field equality, `$and`/`$or`/`$nor` and the
+/// `$eq`, `$ne`, `$gt(e)`, `$lt(e)`, `$in`, `$nin`, `$exists` and
+/// (anchored literal) `$regex` operators.
+pub fn matches(doc: &Value, query: &Value) -> bool {
+    let query = match query.as_object() {
+        Some(query) => query,
+        None => return true,
+    };
+
+    for (key, condition) in query {
+        let ok = match key.as_str() {
+            "$and" => condition
+                .as_array()
+                .map(|items| items.iter().all(|q| matches(doc, q)))
+                .unwrap_or(false),
+            "$or" => condition
+                .as_array()
+                .map(|items| items.iter().any(|q| matches(doc, q)))
+                .unwrap_or(false),
+            "$nor" => condition
+                .as_array()
+                .map(|items| !items.iter().any(|q| matches(doc, q)))
+                .unwrap_or(false),
+            _ => matches_condition(lookup(doc, key), condition),
+        };
+        if !ok {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+fn insert_path(target: &mut Map<String, Value>, path: &[&str], value: &Value) {
+    if path.len() == 1 {
+        target.insert(path[0].to_string(), value.clone());
+        return;
+    }
+    let child = target
+        .entry(path[0].to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Some(child) = child.as_object_mut() {
+        insert_path(child, &path[1..], value);
+    }
+}
+
+/// Keeps only the comma-separated `projection` paths (and `_id`) of `doc`.
+/// An empty projection keeps everything.
+pub fn project(doc: &Value, projection: &str) -> Value {
+    let paths: Vec<&str> = projection.split(',').filter(|p| !p.is_empty()).collect();
+    if paths.is_empty() {
+        return doc.clone();
+    }
+
+    let mut result = Map::new();
+    for path in paths.iter().chain(["_id"].iter()) {
+        let path = path.trim_end_matches('.');
+        if let Some(value) = lookup(doc, path) {
+            let names: Vec<&str> = path.split('.').collect();
+            insert_path(&mut result, &names, value);
+        }
+    }
+    return Value::Object(result);
+}
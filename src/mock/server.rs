@@ -0,0 +1,176 @@
+use super::MockNbi;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Method;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// A `MockNbi` served over HTTP on local ports, the NBI on one and the file
+/// server on another. Stops when dropped.
+pub struct MockServer {
+    /// Base URL of the NBI, e.g. `http://127.0.0.1:41234`
+    pub addr: String,
+    /// Base URL of the file server, on the same host as `addr`
+    pub file_server_addr: String,
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn read_chunked(reader: &mut BufReader<TcpStream>) -> std::io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let size = usize::from_str_radix(line.trim().split(';').next().unwrap_or("0"), 16)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        if size == 0 {
+            // Trailer section ends with an empty line
+            loop {
+                line.clear();
+                reader.read_line(&mut line)?;
+                if line.trim().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        let mut chunk = vec![0; size];
+        reader.read_exact(&mut chunk)?;
+        body.extend(chunk);
+        line.clear();
+        reader.read_line(&mut line)?;
+    }
+}
+
+fn serve_connection(nbi: &MockNbi, stream: TcpStream, file_server: bool) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = Method::from_bytes(parts.next().unwrap_or("GET").as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let target = parts.next().unwrap_or("/").to_string();
+
+    let mut headers = HeaderMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.trim().as_bytes()),
+                HeaderValue::from_str(value.trim()),
+            ) {
+                headers.append(name, value);
+            }
+        }
+    }
+
+    let chunked = headers
+        .get("transfer-encoding")
+        .map(|v| v.as_bytes().eq_ignore_ascii_case(b"chunked"))
+        .unwrap_or(false);
+    let body = if chunked {
+        read_chunked(&mut reader)?
+    } else {
+        let length: usize = headers
+            .get("content-length")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        body
+    };
+
+    let response = nbi.handle_at(file_server, method, &target, &headers, body);
+    let status = response.status;
+    let response_headers = response.headers.clone();
+    let body = response.bytes()?;
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("")
+    )?;
+    for (name, value) in response_headers.iter() {
+        if name != "content-length" {
+            stream.write_all(name.as_str().as_bytes())?;
+            stream.write_all(b": ")?;
+            stream.write_all(value.as_bytes())?;
+            stream.write_all(b"\r\n")?;
+        }
+    }
+    write!(
+        stream,
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    return stream.flush();
+}
+
+/// Accepts connections on `listener` until `stop` is set, serving each on
+/// its own thread.
+fn accept_loop(
+    nbi: MockNbi,
+    listener: TcpListener,
+    stop: Arc<AtomicBool>,
+    file_server: bool,
+) -> JoinHandle<()> {
+    return std::thread::spawn(move || {
+        while !stop.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let nbi = nbi.clone();
+                    std::thread::spawn(move || {
+                        let _ = serve_connection(&nbi, stream, file_server);
+                    });
+                }
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        }
+    });
+}
+
+impl MockNbi {
+    /// Serves this mock over HTTP on `bind` (e.g. `127.0.0.1:0` for any
+    /// free port), and its file contents on another free port of the same
+    /// host. Each connection carries a single request.
+    pub fn serve(&self, bind: &str) -> std::io::Result<MockServer> {
+        let listener = TcpListener::bind(bind)?;
+        listener.set_nonblocking(true)?;
+        let local = listener.local_addr()?;
+        let file_listener = TcpListener::bind((local.ip(), 0))?;
+        file_listener.set_nonblocking(true)?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        return Ok(MockServer {
+            addr: format!("http://{}", local),
+            file_server_addr: format!("http://{}", file_listener.local_addr()?),
+            threads: vec![
+                accept_loop(self.clone(), listener, stop.clone(), false),
+                accept_loop(self.clone(), file_listener, stop.clone(), true),
+            ],
+            stop,
+        });
+    }
+}
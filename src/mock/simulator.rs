@@ -0,0 +1,456 @@
+//! Simulated CPE that executes GenieACS tasks against a `DataNode` data
+//! model, with the read-only checks and CWMP faults a real device would
+//! report. Attach one to a `MockNbi` with `MockNbi::attach_cpe`, or drive it
+//! directly with `SimulatedCpe::apply_task`.
+
+use crate::data_node::DataNode;
+use crate::util::timestamp::{format_timestamp, parse_timestamp};
+use serde_json::Value;
+use std::time::SystemTime;
+
+pub const FAULT_REQUEST_DENIED: u32 = 9001;
+pub const FAULT_INTERNAL_ERROR: u32 = 9002;
+pub const FAULT_INVALID_ARGUMENTS: u32 = 9003;
+pub const FAULT_INVALID_PARAMETER_NAME: u32 = 9005;
+pub const FAULT_INVALID_PARAMETER_TYPE: u32 = 9006;
+pub const FAULT_INVALID_PARAMETER_VALUE: u32 = 9007;
+pub const FAULT_NON_WRITABLE_PARAMETER: u32 = 9008;
+pub const FAULT_DOWNLOAD_FAILURE: u32 = 9010;
+
+/// File type of firmware images in Download requests
+const FIRMWARE_FILE_TYPE: &str = "1 Firmware Upgrade Image";
+
+/// CWMP fault returned by the simulated CPE
+#[derive(PartialEq, Clone, Debug)]
+pub struct CwmpFault {
+    pub code: u32,
+    pub message: String,
+}
+
+impl CwmpFault {
+    pub fn new(code: u32, message: &str) -> Self {
+        return CwmpFault {
+            code,
+            message: message.to_string(),
+        };
+    }
+}
+
+impl std::fmt::Display for CwmpFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cwmp.{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for CwmpFault {}
+
+/// A completed Download request
+#[derive(PartialEq, Clone, Debug)]
+pub struct SimulatedDownload {
+    pub file_name: String,
+    pub file_type: String,
+    pub target_file_name: String,
+}
+
+/// CPE whose data model is a `DataNode` tree rooted above `Device` or
+/// `InternetGatewayDevice`, as returned by `get_parameter_values`.
+///
+/// Parameters (nodes with a type) can only be set when writable. Objects
+/// accept AddObject when they are writable multi-instance objects, and
+/// instances accept DeleteObject when they are writable.
+#[derive(Clone, Debug)]
+pub struct SimulatedCpe {
+    pub model: DataNode,
+    factory_model: DataNode,
+    /// Number of boots since the simulator was created
+    pub boot_count: u32,
+    pub downloads: Vec<SimulatedDownload>,
+    /// Inform event codes raised since the last call to `take_events`,
+    /// e.g. `1 BOOT` and `M Reboot`
+    pub events: Vec<String>,
+}
+
+fn now() -> String {
+    return format_timestamp(SystemTime::now());
+}
+
+/// Removes nodes named after GenieACS metadata (`_object`, `_writable`,
+/// ...), which `DataNode::from_genieacs_json` keeps as empty nodes.
+fn prune_metadata(node: &mut DataNode) {
+    node.subnodes.retain(|name, _| !name.starts_with('_'));
+    for child in node.subnodes.values_mut() {
+        prune_metadata(child);
+    }
+}
+
+/// Resets the values of a copied instance, keeping its structure.
+fn clear_values(node: &mut DataNode, timestamp: &str) {
+    node.value = match node.value_type.as_str() {
+        "" | "xsd:string" | "xsd:base64" | "xsd:hexBinary" => "".to_string(),
+        "xsd:boolean" => "false".to_string(),
+        "xsd:dateTime" => "0001-01-01T00:00:00Z".to_string(),
+        _ => "0".to_string(),
+    };
+    node.timestamp = timestamp.to_string();
+    for child in node.subnodes.values_mut() {
+        clear_values(child, timestamp);
+    }
+}
+
+/// Checks `value` against the XML schema type of a parameter and returns
+/// it in canonical form.
+fn check_value(value: &str, value_type: &str) -> Option<String> {
+    let valid = match value_type {
+        "xsd:boolean" => {
+            return match value {
+                "true" | "1" => Some("true".to_string()),
+                "false" | "0" => Some("false".to_string()),
+                _ => None,
+            }
+        }
+        "xsd:int" => value.parse::<i32>().is_ok(),
+        "xsd:unsignedInt" => value.parse::<u32>().is_ok(),
+        "xsd:long" => value.parse::<i64>().is_ok(),
+        "xsd:unsignedLong" => value.parse::<u64>().is_ok(),
+        "xsd:dateTime" => parse_timestamp(value).is_some(),
+        _ => true,
+    };
+    return valid.then(|| value.to_string());
+}
+
+impl SimulatedCpe {
+    pub fn new(model: DataNode) -> Self {
+        return SimulatedCpe {
+            factory_model: model.clone(),
+            model,
+            boot_count: 0,
+            downloads: Vec::new(),
+            events: Vec::new(),
+        };
+    }
+
+    /// Creates a CPE from a GenieACS device document; the parameter trees
+    /// become the data model and `_id`, `_deviceId` etc. are ignored.
+    pub fn from_device_doc(doc: &Value) -> Self {
+        let mut model = DataNode::from_genieacs_json(doc);
+        prune_metadata(&mut model);
+        return SimulatedCpe::new(model);
+    }
+
+    /// Loads a snapshot, either a serialized `DataNode` or a GenieACS device
+    /// document. GenieACS metadata (`_id`, `_lastInform`, ...) that ended up
+    /// as nodes of a serialized `DataNode` is dropped.
+    pub fn from_snapshot(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let value: Value = serde_json::from_str(json)?;
+        if value.get("subnodes").is_some() {
+            let mut model: DataNode = serde_json::from_value(value)?;
+            prune_metadata(&mut model);
+            return Ok(SimulatedCpe::new(model));
+        }
+        return Ok(SimulatedCpe::from_device_doc(&value));
+    }
+
+    /// The data model rendered as GenieACS device document parameters.
+    pub fn to_device_doc(&self) -> Value {
+        return self.model.to_genieacs_json();
+    }
+
+    /// Name of the root object, `Device` or `InternetGatewayDevice`.
+    pub fn root_name(&self) -> String {
+        return self.model.data_model_root().unwrap_or("Device").to_string();
+    }
+
+    pub fn take_events(&mut self) -> Vec<String> {
+        return std::mem::take(&mut self.events);
+    }
+
+    /// Sets parameters atomically: if any of them faults, none is changed.
+    /// Each entry is `(name, value, type)`; an empty type means the type of
+    /// the parameter in the model.
+    pub fn set_parameter_values(
+        &mut self,
+        values: &[(String, String, String)],
+    ) -> Result<(), CwmpFault> {
+        let mut checked: Vec<(&str, String)> = Vec::new();
+        for (name, value, value_type) in values {
+            let node = match self.model.get_node(name) {
+                Some(node) if !node.value_type.is_empty() && !name.ends_with('.') => node,
+                _ => {
+                    return Err(CwmpFault::new(
+                        FAULT_INVALID_PARAMETER_NAME,
+                        &format!("Invalid parameter name {}", name),
+                    ))
+                }
+            };
+            if !node.writable {
+                return Err(CwmpFault::new(
+                    FAULT_NON_WRITABLE_PARAMETER,
+                    &format!("Attempt to set non-writable parameter {}", name),
+                ));
+            }
+            if !value_type.is_empty() && *value_type != node.value_type {
+                return Err(CwmpFault::new(
+                    FAULT_INVALID_PARAMETER_TYPE,
+                    &format!(
+                        "Invalid type {} for {}, expected {}",
+                        value_type, name, node.value_type
+                    ),
+                ));
+            }
+            let value = check_value(value, &node.value_type).ok_or_else(|| {
+                CwmpFault::new(
+                    FAULT_INVALID_PARAMETER_VALUE,
+                    &format!("Invalid value for {} ({})", name, node.value_type),
+                )
+            })?;
+            checked.push((name, value));
+        }
+
+        let timestamp = now();
+        for (name, value) in checked {
+            let node = self.node_mut(name).unwrap();
+            node.value = value;
+            node.timestamp = timestamp.clone();
+        }
+        return Ok(());
+    }
+
+    /// Adds an instance to a multi-instance object and returns its number.
+    /// The new instance copies the structure of the last existing one with
+    /// values reset.
+    pub fn add_object(&mut self, object_name: &str) -> Result<u32, CwmpFault> {
+        let object_name = object_name.trim_end_matches('.');
+        let invalid = || {
+            CwmpFault::new(
+                FAULT_INVALID_PARAMETER_NAME,
+                &format!("{} is not a multi-instance object", object_name),
+            )
+        };
+        let object = self.model.get_node(object_name).ok_or_else(invalid)?;
+        let not_table = !object.value_type.is_empty()
+            || object.subnodes.keys().any(|k| k.parse::<u32>().is_err());
+        if not_table {
+            return Err(invalid());
+        }
+        if !object.writable {
+            return Err(CwmpFault::new(
+                FAULT_REQUEST_DENIED,
+                &format!("Cannot add instances to {}", object_name),
+            ));
+        }
+
+        let timestamp = now();
+        let instances = object.instance_numbers();
+        let mut instance = match instances.last() {
+            Some(last) => object.subnodes[&last.to_string()].clone(),
+            None => DataNode::new(),
+        };
+        clear_values(&mut instance, &timestamp);
+        instance.writable = true;
+
+        let number = instances.last().map(|n| n + 1).unwrap_or(1);
+        let object = self.node_mut(object_name).unwrap();
+        object.subnodes.insert(number.to_string(), instance);
+        object.timestamp = timestamp;
+        return Ok(number);
+    }
+
+    /// Deletes an instance of a multi-instance object, e.g.
+    /// `Device.NAT.PortMapping.2`.
+    pub fn delete_object(&mut self, object_name: &str) -> Result<(), CwmpFault> {
+        let object_name = object_name.trim_end_matches('.');
+        let invalid = || {
+            CwmpFault::new(
+                FAULT_INVALID_PARAMETER_NAME,
+                &format!("{} is not an object instance", object_name),
+            )
+        };
+        let (parent_name, instance) = object_name.rsplit_once('.').ok_or_else(invalid)?;
+        if instance.parse::<u32>().is_err() {
+            return Err(invalid());
+        }
+        let node = self.model.get_node(object_name).ok_or_else(invalid)?;
+        if !node.writable {
+            return Err(CwmpFault::new(
+                FAULT_REQUEST_DENIED,
+                &format!("Cannot delete {}", object_name),
+            ));
+        }
+
+        let parent = self.node_mut(parent_name).unwrap();
+        parent.subnodes.remove(instance);
+        parent.timestamp = now();
+        return Ok(());
+    }
+
+    pub fn reboot(&mut self) {
+        self.boot(&["M Reboot"]);
+    }
+
+    /// Restores the model the simulator was created with.
+    pub fn factory_reset(&mut self) {
+        self.model = self.factory_model.clone();
+        self.downloads.clear();
+        self.boot(&["0 BOOTSTRAP"]);
+    }
+
+    /// Executes a Download request. A firmware image with a `version` in
+    /// its metadata updates `DeviceInfo.SoftwareVersion` and reboots the
+    /// CPE.
+    pub fn download(
+        &mut self,
+        file_name: &str,
+        file_type: &str,
+        target_file_name: &str,
+        version: Option<&str>,
+    ) -> Result<(), CwmpFault> {
+        if file_name.is_empty() {
+            return Err(CwmpFault::new(FAULT_INVALID_ARGUMENTS, "Missing file name"));
+        }
+        self.downloads.push(SimulatedDownload {
+            file_name: file_name.to_string(),
+            file_type: file_type.to_string(),
+            target_file_name: target_file_name.to_string(),
+        });
+        self.events.push("7 TRANSFER COMPLETE".to_string());
+        self.events.push("M Download".to_string());
+
+        if file_type == FIRMWARE_FILE_TYPE {
+            if let Some(version) = version.filter(|v| !v.is_empty()) {
+                let path = format!("{}.DeviceInfo.SoftwareVersion", self.root_name());
+                let timestamp = now();
+                if let Some(node) = self.node_mut(&path) {
+                    node.value = version.to_string();
+                    node.timestamp = timestamp;
+                }
+            }
+            self.boot(&[]);
+        }
+        return Ok(());
+    }
+
+    /// Refreshes the timestamps below `object_name`, as a GetParameterValues
+    /// of the subtree would.
+    pub fn refresh(&mut self, object_name: &str) -> Result<(), CwmpFault> {
+        let timestamp = now();
+        let node = self.node_mut(object_name).ok_or_else(|| {
+            CwmpFault::new(
+                FAULT_INVALID_PARAMETER_NAME,
+                &format!("Invalid parameter name {}", object_name),
+            )
+        })?;
+        fn touch(node: &mut DataNode, timestamp: &str) {
+            node.timestamp = timestamp.to_string();
+            for child in node.subnodes.values_mut() {
+                touch(child, timestamp);
+            }
+        }
+        touch(node, &timestamp);
+        return Ok(());
+    }
+
+    /// Executes a GenieACS task document such as
+    /// `{"name": "setParameterValues", "parameterValues": [...]}`.
+    /// `file_metadata` looks up the metadata of a file on the ACS by name,
+    /// for download tasks.
+    pub fn apply_task(
+        &mut self,
+        task: &Value,
+        file_metadata: &dyn Fn(&str) -> Option<Value>,
+    ) -> Result<(), CwmpFault> {
+        let str_field = |name: &str| task.get(name).and_then(|v| v.as_str()).unwrap_or("");
+        let missing =
+            |name: &str| CwmpFault::new(FAULT_INVALID_ARGUMENTS, &format!("Task has no {}", name));
+
+        return match str_field("name") {
+            "setParameterValues" => {
+                let params = task
+                    .get("parameterValues")
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| missing("parameterValues"))?;
+                let mut values = Vec::new();
+                for param in params {
+                    let item = |i: usize| param.get(i).unwrap_or(&Value::Null);
+                    let name = item(0).as_str().ok_or_else(|| missing("parameter name"))?;
+                    let value = match item(1) {
+                        Value::String(s) => s.clone(),
+                        Value::Bool(b) => b.to_string(),
+                        Value::Number(n) => n.to_string(),
+                        _ => return Err(missing("parameter value")),
+                    };
+                    let value_type = item(2).as_str().unwrap_or("");
+                    values.push((name.to_string(), value, value_type.to_string()));
+                }
+                self.set_parameter_values(&values)
+            }
+            "getParameterValues" => {
+                let names = task
+                    .get("parameterNames")
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| missing("parameterNames"))?;
+                for name in names.iter().filter_map(|n| n.as_str()) {
+                    self.refresh(name)?;
+                }
+                Ok(())
+            }
+            "refreshObject" => self.refresh(str_field("objectName")),
+            "addObject" => self.add_object(str_field("objectName")).map(|_| ()),
+            "deleteObject" => self.delete_object(str_field("objectName")),
+            "reboot" => {
+                self.reboot();
+                Ok(())
+            }
+            "factoryReset" => {
+                self.factory_reset();
+                Ok(())
+            }
+            "download" => {
+                let file = match str_field("fileName") {
+                    "" => str_field("file"),
+                    file => file,
+                };
+                let metadata = file_metadata(file).ok_or_else(|| {
+                    CwmpFault::new(FAULT_DOWNLOAD_FAILURE, &format!("File {} not found", file))
+                })?;
+                let metadata = metadata.get("metadata").unwrap_or(&metadata);
+                let file_type = match str_field("fileType") {
+                    "" => metadata
+                        .get("fileType")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(""),
+                    file_type => file_type,
+                };
+                let version = metadata.get("version").and_then(|v| v.as_str());
+                self.download(file, file_type, str_field("targetFileName"), version)
+            }
+            name => Err(CwmpFault::new(
+                FAULT_INTERNAL_ERROR,
+                &format!("Unsupported task {}", name),
+            )),
+        };
+    }
+
+    fn node_mut(&mut self, path: &str) -> Option<&mut DataNode> {
+        let mut node = &mut self.model;
+        for name in path.trim_end_matches('.').split('.') {
+            if name.is_empty() {
+                continue;
+            }
+            node = node.subnodes.get_mut(name)?;
+        }
+        return Some(node);
+    }
+
+    fn boot(&mut self, events: &[&str]) {
+        self.boot_count += 1;
+        self.events.push("1 BOOT".to_string());
+        self.events.extend(events.iter().map(|e| e.to_string()));
+
+        let timestamp = now();
+        let path = format!("{}.DeviceInfo.UpTime", self.root_name());
+        if let Some(node) = self.node_mut(&path) {
+            node.value = "0".to_string();
+            node.timestamp = timestamp;
+        }
+    }
+}
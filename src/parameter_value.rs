@@ -1,9 +1,11 @@
+use crate::redact::redact_value;
 use crate::util::accessor::*;
 use serde::Serializer;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-#[derive(Deserialize, PartialEq, Clone, Debug)]
+/// `Debug` output masks the value of sensitive parameters, see `redact`.
+#[derive(Deserialize, PartialEq, Clone)]
 #[repr(C)]
 pub struct ParameterValue {
     #[serde(default = "unset_str")]
@@ -32,6 +34,16 @@ impl ParameterValue {
     }
 }
 
+impl std::fmt::Debug for ParameterValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParameterValue")
+            .field("parameter", &self.parameter)
+            .field("value", &redact_value(&self.parameter, &self.value))
+            .field("value_type", &self.value_type)
+            .finish()
+    }
+}
+
 impl Serialize for ParameterValue {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
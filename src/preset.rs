@@ -0,0 +1,111 @@
+use crate::util::accessor::*;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+fn unset_weight() -> i64 {
+    0
+}
+
+/// GenieACS stores `"args": null` for provisions called without arguments.
+fn null_as_empty<'de, D>(deserializer: D) -> Result<Vec<Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let args: Option<Vec<Value>> = Deserialize::deserialize(deserializer)?;
+    Ok(args.unwrap_or_default())
+}
+
+fn default_channel() -> String {
+    "default".to_string()
+}
+
+/// What a preset applies to matching devices, as listed in its
+/// `configurations`.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PresetConfiguration {
+    /// Runs a provision script with the given arguments
+    Provision {
+        name: String,
+        #[serde(default, deserialize_with = "null_as_empty")]
+        args: Vec<Value>,
+    },
+    /// Sets a parameter
+    Value {
+        name: String,
+        value: Value,
+    },
+    /// Refreshes a parameter older than `age` seconds
+    Age {
+        name: String,
+        age: Value,
+    },
+    AddTag {
+        tag: String,
+    },
+    DeleteTag {
+        tag: String,
+    },
+    /// Makes sure an instance identified by `object` exists below `name`
+    AddObject {
+        name: String,
+        object: String,
+    },
+    DeleteObject {
+        name: String,
+        object: String,
+    },
+    /// Any other entry, e.g. of a type added by a newer GenieACS, kept as is
+    /// so that it survives being read and written back
+    #[serde(untagged)]
+    Raw(Value),
+}
+
+/// A GenieACS preset, as stored under `/presets`.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[repr(C)]
+pub struct AcsPreset {
+    #[serde(default = "unset_str", rename = "_id")]
+    pub name: String,
+
+    /// Presets with a higher weight are applied later and win conflicts
+    #[serde(default = "unset_weight")]
+    pub weight: i64,
+
+    /// Faults in one channel do not block presets in other channels
+    #[serde(default = "default_channel")]
+    pub channel: String,
+
+    /// Cron-like schedule, empty when the preset applies on every inform
+    #[serde(default = "unset_str")]
+    pub schedule: String,
+
+    /// Inform events the preset is restricted to, e.g. `"0 BOOTSTRAP": true`
+    #[serde(default)]
+    pub events: BTreeMap<String, bool>,
+
+    /// Device query (or expression) selecting the devices the preset
+    /// applies to; empty for all devices
+    #[serde(default = "unset_str")]
+    pub precondition: String,
+
+    /// Entries this crate does not know are kept as
+    /// `PresetConfiguration::Raw`
+    #[serde(default)]
+    pub configurations: Vec<PresetConfiguration>,
+}
+
+impl AcsPreset {
+    pub fn new(name: &str) -> Self {
+        return AcsPreset {
+            name: name.to_string(),
+            weight: 0,
+            channel: default_channel(),
+            schedule: "".to_string(),
+            events: BTreeMap::new(),
+            precondition: "".to_string(),
+            configurations: Vec::new(),
+        };
+    }
+}
@@ -0,0 +1,45 @@
+use crate::preset::PresetConfiguration;
+use crate::util::accessor::*;
+use crate::util::file_name::decode_file_name;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// A GenieACS provision script, as stored under `/provisions`. Presets run
+/// it with arguments available to the script as `args`.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[repr(C)]
+pub struct AcsProvision {
+    #[serde(default = "unset_str", rename = "_id")]
+    pub name: String,
+
+    /// JavaScript source of the provision
+    #[serde(default = "unset_str")]
+    pub script: String,
+}
+
+impl AcsProvision {
+    pub fn new(name: &str, script: &str) -> Self {
+        return AcsProvision {
+            name: name.to_string(),
+            script: script.to_string(),
+        };
+    }
+
+    /// Reads a provision from a script file named after the provision,
+    /// e.g. `provisions/default.js` for the provision `default`. The name is
+    /// percent-decoded as in configuration snapshots.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let name = decode_file_name(path)?;
+        let script = std::fs::read_to_string(path)?;
+        return Ok(AcsProvision::new(&name, &script));
+    }
+
+    /// Preset configuration running this provision with `args`.
+    pub fn call(&self, args: Vec<Value>) -> PresetConfiguration {
+        return PresetConfiguration::Provision {
+            name: self.name.clone(),
+            args,
+        };
+    }
+}
@@ -1,6 +1,10 @@
+use crate::file::{AcsFile, FileType};
 use crate::util::accessor::*;
 use serde::{Deserialize, Serialize};
 
+/// A `download` task. GenieACS picks the file by `fileName` (or, in older
+/// releases, `file`); `fileType` is passed to the CPE as the Download
+/// RPC's FileType and `targetFileName` as the name to store it under.
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 #[repr(C)]
 pub struct DownloadCommand {
@@ -8,6 +12,16 @@ pub struct DownloadCommand {
     pub name: String,
     #[serde(default = "unset_str")]
     pub file: String,
+    #[serde(default, rename = "fileType", skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<FileType>,
+    #[serde(default, rename = "fileName", skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    #[serde(
+        default,
+        rename = "targetFileName",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target_file_name: Option<String>,
 }
 
 impl DownloadCommand {
@@ -15,6 +29,44 @@ impl DownloadCommand {
         return DownloadCommand {
             name: "download".to_string(),
             file: file.to_string(),
+            file_type: None,
+            file_name: None,
+            target_file_name: None,
         };
     }
+
+    /// Download of a file in the file store, with the file type from its
+    /// metadata.
+    pub fn for_file(file: &AcsFile) -> Self {
+        return DownloadCommand::new(&file.name)
+            .file_name(&file.name)
+            .file_type(file.metadata.file_type.clone());
+    }
+
+    pub fn file_type(mut self, file_type: FileType) -> Self {
+        self.file_type = Some(file_type);
+        return self;
+    }
+
+    pub fn file_name(mut self, file_name: &str) -> Self {
+        self.file_name = Some(file_name.to_string());
+        return self;
+    }
+
+    pub fn target_file_name(mut self, target_file_name: &str) -> Self {
+        self.target_file_name = Some(target_file_name.to_string());
+        return self;
+    }
+}
+
+impl From<&str> for DownloadCommand {
+    fn from(file: &str) -> Self {
+        return DownloadCommand::new(file);
+    }
+}
+
+impl From<String> for DownloadCommand {
+    fn from(file: String) -> Self {
+        return DownloadCommand::new(&file);
+    }
 }
@@ -2,6 +2,7 @@ use crate::parameter_value::ParameterValue;
 use crate::util::accessor::*;
 use serde::{Deserialize, Serialize};
 
+/// `Debug` output masks sensitive values through `ParameterValue`.
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 #[repr(C)]
 pub struct SetParameterValues {
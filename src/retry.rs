@@ -0,0 +1,100 @@
+use crate::transport::{TransportError, TransportErrorKind};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// When and how often failed requests to the ACS are retried.
+///
+/// Idempotent requests (GET, PUT, DELETE) are retried on timeouts,
+/// connection failures and `retry_statuses`. Non-idempotent ones (task
+/// POSTs) are only retried when the connection could not be established,
+/// so a task is never queued twice, unless `retry_non_idempotent` is set.
+#[derive(PartialEq, Clone, Debug)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between attempts
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after each retry
+    pub multiplier: f64,
+    /// Random fraction (0.0 - 1.0) of the delay that is added or removed
+    pub jitter: f64,
+    /// HTTP statuses considered transient
+    pub retry_statuses: Vec<u16>,
+    /// Whether request timeouts are retried
+    pub retry_timeouts: bool,
+    /// Whether non-idempotent requests are retried like idempotent ones
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        return RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retry_statuses: vec![429, 502, 503, 504],
+            retry_timeouts: true,
+            retry_non_idempotent: false,
+        };
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that never retries
+    pub fn none() -> Self {
+        return RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        };
+    }
+
+    /// Whether a request failing with `error` should be attempted again.
+    pub fn should_retry_error(
+        &self,
+        attempt: u32,
+        idempotent: bool,
+        error: &TransportError,
+    ) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+        if error.kind == TransportErrorKind::Connect {
+            return true;
+        }
+        if !idempotent && !self.retry_non_idempotent {
+            return false;
+        }
+        return error.kind == TransportErrorKind::Timeout && self.retry_timeouts;
+    }
+
+    /// Whether a request answered with `status` should be attempted again.
+    pub fn should_retry_status(&self, attempt: u32, idempotent: bool, status: u16) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+        if !idempotent && !self.retry_non_idempotent {
+            return false;
+        }
+        return self.retry_statuses.contains(&status);
+    }
+
+    /// Delay before attempt number `attempt + 1`, with jitter applied.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let base = self.initial_backoff.as_secs_f64()
+            * self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let base = base.min(self.max_backoff.as_secs_f64());
+
+        // Uniform value in [-1.0, 1.0) without pulling in an RNG crate
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(attempt);
+        let random = (hasher.finish() % 2000) as f64 / 1000.0 - 1.0;
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        return Duration::from_secs_f64((base * (1.0 + jitter * random)).max(0.0));
+    }
+}
@@ -0,0 +1,277 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::blocking::Client;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE,
+};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::time::Duration;
+
+/// Body of an `HttpRequest`.
+pub enum HttpBody {
+    Empty,
+    Bytes(Vec<u8>),
+    /// Streamed body of the given length, if known. Cannot be retried.
+    Reader(Box<dyn Read + Send>, Option<u64>),
+}
+
+/// HTTP request as handed to an `HttpTransport`.
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: HttpBody,
+    /// Total timeout of the request, including sending the body and reading
+    /// the response; `None` for no limit beyond the transport's own
+    pub timeout: Option<Duration>,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, url: &str) -> Self {
+        return HttpRequest {
+            method,
+            url: url.to_string(),
+            headers: HeaderMap::new(),
+            body: HttpBody::Empty,
+            timeout: None,
+        };
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        self.headers.insert(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_str(value)?,
+        );
+        return Ok(self);
+    }
+
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers.extend(headers);
+        return self;
+    }
+
+    pub fn basic_auth(mut self, username: &str, password: Option<&str>) -> Self {
+        let credentials = format!("{}:{}", username, password.unwrap_or(""));
+        let mut value = HeaderValue::from_str(&format!("Basic {}", STANDARD.encode(credentials)))
+            .expect("base64 is a valid header value");
+        value.set_sensitive(true);
+        self.headers.insert(AUTHORIZATION, value);
+        return self;
+    }
+
+    pub fn bearer_auth(mut self, token: &str) -> Self {
+        if let Ok(mut value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+            value.set_sensitive(true);
+            self.headers.insert(AUTHORIZATION, value);
+        }
+        return self;
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        return self;
+    }
+
+    /// Sets or removes the total timeout, e.g. for large streamed bodies.
+    pub fn timeout_opt(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        return self;
+    }
+
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = HttpBody::Bytes(body);
+        return self;
+    }
+
+    /// Streams the body from `reader`; `length` becomes the
+    /// `Content-Length` when known.
+    pub fn body_reader(mut self, reader: Box<dyn Read + Send>, length: Option<u64>) -> Self {
+        self.body = HttpBody::Reader(reader, length);
+        return self;
+    }
+
+    pub fn json<T: Serialize + ?Sized>(mut self, value: &T) -> Result<Self, serde_json::Error> {
+        self.body = HttpBody::Bytes(serde_json::to_vec(value)?);
+        self.headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        return Ok(self);
+    }
+
+    /// The body, unless it is streamed.
+    pub fn body_bytes(&self) -> Option<&[u8]> {
+        return match &self.body {
+            HttpBody::Empty => Some(&[]),
+            HttpBody::Bytes(bytes) => Some(bytes),
+            HttpBody::Reader(_, _) => None,
+        };
+    }
+
+    /// Copies the request for another attempt. Streamed requests cannot be
+    /// copied.
+    pub fn try_clone(&self) -> Option<Self> {
+        let body = match &self.body {
+            HttpBody::Empty => HttpBody::Empty,
+            HttpBody::Bytes(bytes) => HttpBody::Bytes(bytes.clone()),
+            HttpBody::Reader(_, _) => return None,
+        };
+        return Some(HttpRequest {
+            method: self.method.clone(),
+            url: self.url.clone(),
+            headers: self.headers.clone(),
+            body,
+            timeout: self.timeout,
+        });
+    }
+}
+
+/// HTTP response as returned by an `HttpTransport`. The body is streamed.
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl HttpResponse {
+    /// Response with an in-memory body, mostly useful for fake transports.
+    pub fn from_bytes(status: StatusCode, body: Vec<u8>) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+        return HttpResponse {
+            status,
+            headers,
+            body: Box::new(std::io::Cursor::new(body)),
+        };
+    }
+
+    pub fn status(&self) -> StatusCode {
+        return self.status;
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        return &self.headers;
+    }
+
+    /// Length announced in `Content-Length`, if any.
+    pub fn content_length(&self) -> Option<u64> {
+        return self
+            .headers
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+    }
+
+    pub fn bytes(mut self) -> Result<Vec<u8>, std::io::Error> {
+        let mut bytes = Vec::new();
+        self.body.read_to_end(&mut bytes)?;
+        return Ok(bytes);
+    }
+
+    pub fn text(self) -> Result<String, Box<dyn std::error::Error>> {
+        return Ok(String::from_utf8(self.bytes()?)?);
+    }
+
+    pub fn json<T: DeserializeOwned>(self) -> Result<T, Box<dyn std::error::Error>> {
+        return Ok(serde_json::from_slice(&self.bytes()?)?);
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportErrorKind {
+    /// The connection could not be established; the request was not sent.
+    Connect,
+    /// No complete response within the timeout; the request may have been
+    /// processed.
+    Timeout,
+    Other,
+}
+
+/// Failure to obtain an HTTP response.
+#[derive(Debug)]
+pub struct TransportError {
+    pub kind: TransportErrorKind,
+    pub source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl TransportError {
+    pub fn new<E>(kind: TransportErrorKind, source: E) -> Self
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        return TransportError {
+            kind,
+            source: source.into(),
+        };
+    }
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return self.source.fmt(f);
+    }
+}
+
+impl std::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return Some(self.source.as_ref());
+    }
+}
+
+/// The HTTP stack used by `AcsConnection`. `ReqwestTransport` is the
+/// default; tests can inject a fake one and users can plug in another
+/// client.
+pub trait HttpTransport: Send + Sync {
+    fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError>;
+}
+
+/// `HttpTransport` based on a blocking `reqwest` client.
+#[derive(Clone)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: Client) -> Self {
+        return ReqwestTransport { client };
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let mut builder = self
+            .client
+            .request(request.method, &request.url)
+            .headers(request.headers);
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+        builder = match request.body {
+            HttpBody::Empty => builder,
+            HttpBody::Bytes(bytes) => builder.body(bytes),
+            HttpBody::Reader(reader, Some(length)) => {
+                builder.body(reqwest::blocking::Body::sized(reader, length))
+            }
+            HttpBody::Reader(reader, None) => builder.body(reqwest::blocking::Body::new(reader)),
+        };
+
+        let response = builder.send().map_err(|err| {
+            let kind = if err.is_connect() {
+                TransportErrorKind::Connect
+            } else if err.is_timeout() {
+                TransportErrorKind::Timeout
+            } else {
+                TransportErrorKind::Other
+            };
+            TransportError::new(kind, err)
+        })?;
+
+        return Ok(HttpResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: Box::new(response),
+        });
+    }
+}
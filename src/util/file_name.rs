@@ -0,0 +1,39 @@
+use std::path::Path;
+use urlencoding::{decode, encode};
+
+/// File name for the resource `name`, percent-encoded so that names with
+/// `/` or other characters unsafe in paths can be stored, e.g.
+/// `inform%2Fwan.js` for `inform/wan`.
+pub fn encode_file_name(name: &str, extension: &str) -> String {
+    return format!("{}.{}", encode(name), extension);
+}
+
+/// Resource name of a file written by `encode_file_name`: its stem with
+/// percent-encoding undone.
+pub fn decode_file_name(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| format!("Invalid file name {}", path.display()))?;
+    return Ok(decode(stem)?.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_names() {
+        for name in ["default", "inform/wan", "a b%c", "bootstrap.js"] {
+            let file_name = encode_file_name(name, "js");
+            assert!(!file_name.contains('/'));
+            assert_eq!(decode_file_name(Path::new(&file_name)).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn decodes_stems_with_directories() {
+        let path = Path::new("provisions/inform%2Fwan.js");
+        assert_eq!(decode_file_name(path).unwrap(), "inform/wan");
+    }
+}
@@ -1 +1,5 @@
 pub mod accessor;
+pub mod file_name;
+pub mod path;
+pub mod timestamp;
+pub mod version;
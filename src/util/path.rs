@@ -0,0 +1,254 @@
+/// One `Key="value"` condition of an instance selector.
+#[derive(PartialEq, Clone, Debug)]
+pub struct SelectorKey {
+    pub key: String,
+    pub value: String,
+}
+
+/// A segment of a parameter path: either a plain name (or instance number)
+/// or an instance selector such as `[MACAddress="aa:bb:cc:dd:ee:ff"]`.
+#[derive(PartialEq, Clone, Debug)]
+pub enum PathSegment {
+    Name(String),
+    Selector(Vec<SelectorKey>),
+}
+
+/// Whether `path` contains an instance selector.
+pub fn has_selector(path: &str) -> bool {
+    return path.contains('[');
+}
+
+/// Byte offset of the first of `targets` in `text` that is outside of any
+/// selector, e.g. to split `Host.[MACAddress="aa:bb"].Name:xsd:string=x`
+/// after the path.
+pub fn find_outside_selectors(text: &str, targets: &[char]) -> Option<usize> {
+    let mut in_brackets = false;
+    let mut in_quotes = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' if in_brackets => in_quotes = !in_quotes,
+            '[' if !in_quotes => in_brackets = true,
+            ']' if !in_quotes => in_brackets = false,
+            c if !in_brackets && targets.contains(&c) => return Some(i),
+            _ => {}
+        }
+    }
+    return None;
+}
+
+/// Splits a parameter path into segments. Dots and brackets inside quoted
+/// selector values are kept verbatim, so `[IPAddress="192.168.1.2"]` works.
+/// A single trailing dot (an object path) is allowed; other empty segments
+/// are rejected.
+pub fn split_path(path: &str) -> Result<Vec<PathSegment>, Box<dyn std::error::Error>> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut in_brackets = false;
+    let mut in_quotes = false;
+
+    for c in path.chars() {
+        match c {
+            '"' if in_brackets => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            '[' if !in_quotes => {
+                if in_brackets {
+                    return Err(Box::from(format!("Nested selector in path {}", path)));
+                }
+                in_brackets = true;
+                current.push(c);
+            }
+            ']' if !in_quotes => {
+                if !in_brackets {
+                    return Err(Box::from(format!("Unbalanced ']' in path {}", path)));
+                }
+                in_brackets = false;
+                current.push(c);
+            }
+            '.' if !in_brackets => {
+                if current.is_empty() {
+                    return Err(Box::from(format!("Empty segment in path {}", path)));
+                }
+                segments.push(parse_segment(&current)?);
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if in_brackets || in_quotes {
+        return Err(Box::from(format!("Unterminated selector in path {}", path)));
+    }
+    if !current.is_empty() {
+        segments.push(parse_segment(&current)?);
+    }
+
+    return Ok(segments);
+}
+
+/// Parses `[Key="value",Other=value]`. A bare `[name]` is shorthand for
+/// `[Alias="name"]`.
+fn parse_segment(segment: &str) -> Result<PathSegment, Box<dyn std::error::Error>> {
+    let inner = match segment.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Some(inner) => inner,
+        None => {
+            if segment.contains('[') || segment.contains(']') {
+                return Err(Box::from(format!("Malformed selector {}", segment)));
+            }
+            return Ok(PathSegment::Name(segment.to_string()));
+        }
+    };
+
+    let mut keys = Vec::new();
+    for condition in split_conditions(inner) {
+        let condition = condition.trim();
+        let (key, value) = match condition.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => ("Alias", condition),
+        };
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        if key.is_empty() {
+            return Err(Box::from(format!("Empty key in selector {}", segment)));
+        }
+        keys.push(SelectorKey {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+    }
+
+    if keys.is_empty() {
+        return Err(Box::from(format!("Empty selector {}", segment)));
+    }
+    return Ok(PathSegment::Selector(keys));
+}
+
+/// Splits selector conditions on commas outside of quotes.
+fn split_conditions(inner: &str) -> Vec<String> {
+    let mut conditions = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in inner.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            ',' if !in_quotes => {
+                conditions.push(current.clone());
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() {
+        conditions.push(current);
+    }
+
+    return conditions;
+}
+
+/// The part of `path` before its first selector, e.g. `Device.Hosts.Host`
+/// for `Device.Hosts.Host.[MACAddress="aa:bb"].IPAddress`.
+pub fn selector_prefix(path: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut names = Vec::new();
+    for segment in split_path(path)? {
+        match segment {
+            PathSegment::Name(name) => names.push(name),
+            PathSegment::Selector(_) => break,
+        }
+    }
+    return Ok(names.join("."));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str, value: &str) -> SelectorKey {
+        return SelectorKey {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+    }
+
+    #[test]
+    fn splits_plain_paths() {
+        assert_eq!(
+            split_path("Device.Hosts.Host.2.").unwrap(),
+            vec![
+                PathSegment::Name("Device".to_string()),
+                PathSegment::Name("Hosts".to_string()),
+                PathSegment::Name("Host".to_string()),
+                PathSegment::Name("2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_selectors_with_quoted_values() {
+        let segments =
+            split_path(r#"Device.IP.[IPAddress="192.168.1.2",Enable=true].Status"#).unwrap();
+        assert_eq!(
+            segments[2],
+            PathSegment::Selector(vec![key("IPAddress", "192.168.1.2"), key("Enable", "true")])
+        );
+        assert_eq!(segments[3], PathSegment::Name("Status".to_string()));
+
+        let segments = split_path(r#"Device.WiFi.SSID.[SSID="a,b]c"]"#).unwrap();
+        assert_eq!(
+            segments[3],
+            PathSegment::Selector(vec![key("SSID", "a,b]c")])
+        );
+    }
+
+    #[test]
+    fn bare_selector_is_alias() {
+        assert_eq!(
+            split_path("Device.NAT.PortMapping.[web]").unwrap()[3],
+            PathSegment::Selector(vec![key("Alias", "web")])
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        for path in [
+            "Device..Hosts",
+            ".Device",
+            "Device.Hosts.[",
+            "Device.Hosts.]",
+            "Device.Hosts.[[a]]",
+            r#"Device.Hosts.[MACAddress="aa]"#,
+            "Device.Hosts.[]",
+            "Device.Hosts.[=x]",
+            "Device.Hosts.a[b]",
+        ] {
+            assert!(split_path(path).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn prefix_stops_at_first_selector() {
+        assert_eq!(
+            selector_prefix(r#"Device.Hosts.Host.[MACAddress="aa:bb"].IPAddress"#).unwrap(),
+            "Device.Hosts.Host"
+        );
+        assert!(has_selector("Device.Hosts.Host.[a]"));
+        assert!(!has_selector("Device.Hosts.Host.1"));
+    }
+
+    #[test]
+    fn finds_separators_outside_selectors() {
+        let arg = r#"Device.Hosts.Host.[MACAddress="aa:bb=cc"].HostName:xsd:string=x"#;
+        let end = find_outside_selectors(arg, &[':', '=']).unwrap();
+        assert_eq!(
+            &arg[..end],
+            r#"Device.Hosts.Host.[MACAddress="aa:bb=cc"].HostName"#
+        );
+        assert_eq!(find_outside_selectors("Device.[a=b]", &['=']), None);
+    }
+}
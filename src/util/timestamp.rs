@@ -0,0 +1,149 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    return era * 146097 + doe - 719468;
+}
+
+/// Proleptic Gregorian (year, month, day) for days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    return (year, month, day);
+}
+
+/// Formats a time the way GenieACS stores timestamps
+/// (e.g. `2024-05-01T12:34:56.789Z`).
+pub fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as i64;
+    let (year, month, day) = civil_from_days(secs / 86400);
+    let rem = secs % 86400;
+    return format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    );
+}
+
+/// Parses an ISO 8601 UTC timestamp as stored by GenieACS
+/// (e.g. `2024-05-01T12:34:56.789Z`).
+pub fn parse_timestamp(timestamp: &str) -> Option<SystemTime> {
+    let timestamp = timestamp.trim();
+    let (date, time) = timestamp.split_once('T')?;
+    let time = time.strip_suffix('Z').unwrap_or(time);
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: i64 = date_parts.next()?.parse().ok()?;
+    let day: i64 = date_parts.next()?.parse().ok()?;
+
+    let (hms, fraction) = match time.split_once('.') {
+        Some((hms, fraction)) => (hms, fraction),
+        None => (time, ""),
+    };
+    let mut time_parts = hms.splitn(3, ':');
+    let hour: i64 = time_parts.next()?.parse().ok()?;
+    let minute: i64 = time_parts.next()?.parse().ok()?;
+    let second: i64 = time_parts.next()?.parse().ok()?;
+
+    let days_in_month = match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if day < 1 || day > days_in_month || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut nanos: u32 = 0;
+    if !fraction.is_empty() {
+        let digits: String = fraction.chars().take(9).collect();
+        let scale = 10u32.pow(9 - digits.len() as u32);
+        nanos = digits.parse::<u32>().ok()? * scale;
+    }
+
+    let secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    if secs < 0 {
+        return None;
+    }
+    return Some(UNIX_EPOCH + Duration::new(secs as u64, nanos));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(time: SystemTime) -> u64 {
+        return time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    }
+
+    #[test]
+    fn civil_days_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        for days in [-800_000, -1, 0, 59, 60, 11016, 11017, 19_000, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn parses_genieacs_timestamps() {
+        let time = parse_timestamp("2024-05-01T12:34:56.789Z").unwrap();
+        assert_eq!(secs(time), 1714566896);
+        assert_eq!(
+            time.duration_since(UNIX_EPOCH).unwrap().subsec_millis(),
+            789
+        );
+        assert_eq!(secs(parse_timestamp("1970-01-01T00:00:00Z").unwrap()), 0);
+        assert_eq!(
+            secs(parse_timestamp("2024-02-29T00:00:00").unwrap()),
+            1709164800
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_timestamps() {
+        for timestamp in [
+            "",
+            "2024-05-01",
+            "2024-13-01T00:00:00Z",
+            "2023-02-29T00:00:00Z",
+            "2024-05-01T24:00:00Z",
+            "1969-12-31T23:59:59Z",
+            "2024-05-01Tnoon",
+        ] {
+            assert_eq!(parse_timestamp(timestamp), None, "{}", timestamp);
+        }
+    }
+
+    #[test]
+    fn format_round_trips() {
+        for timestamp in ["2024-05-01T12:34:56.789Z", "2000-02-29T23:59:59.000Z"] {
+            let time = parse_timestamp(timestamp).unwrap();
+            assert_eq!(format_timestamp(time), timestamp);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::cmp::Ordering;
+
+/// Compares version strings such as `1.10.2` and `1.9.7-rc1` segment by
+/// segment. Numeric segments compare as numbers, others as text. When one
+/// version extends the other, a numeric extra segment makes it newer
+/// (`1.9.7.1` > `1.9.7`), while a non-numeric one marks a pre-release that
+/// sorts before the release, as in semver (`1.9.7-rc1` < `1.9.7`).
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let segments = |v: &str| -> Vec<String> {
+        return v
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    };
+    let (a, b) = (segments(a), segments(b));
+    for (a, b) in a.iter().zip(b.iter()) {
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            (Ok(_), Err(_)) => Ordering::Greater,
+            (Err(_), Ok(_)) => Ordering::Less,
+            _ => a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    let shared = a.len().min(b.len());
+    let is_pre_release =
+        |extra: Option<&String>| extra.is_some_and(|segment| segment.parse::<u64>().is_err());
+    return match a.len().cmp(&b.len()) {
+        Ordering::Greater if is_pre_release(a.get(shared)) => Ordering::Less,
+        Ordering::Less if is_pre_release(b.get(shared)) => Ordering::Greater,
+        ordering => ordering,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_numeric_segments_as_numbers() {
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.9.7", "1.10.2"), Ordering::Less);
+    }
+
+    #[test]
+    fn sorts_pre_releases_before_the_release() {
+        assert_eq!(compare_versions("1.9.7", "1.9.7-rc1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.9.7-rc1", "1.9.7"), Ordering::Less);
+        assert_eq!(compare_versions("1.9.7-rc1", "1.9.7-rc2"), Ordering::Less);
+        assert_eq!(compare_versions("1.9.7-rc1", "1.9.6"), Ordering::Greater);
+    }
+
+    #[test]
+    fn extra_numeric_segments_are_newer() {
+        assert_eq!(compare_versions("1.9.7.1", "1.9.7"), Ordering::Greater);
+        assert_eq!(compare_versions("1.9", "1.9.0"), Ordering::Less);
+    }
+
+    #[test]
+    fn equal_versions() {
+        assert_eq!(compare_versions("1.9.7", "1.9.7"), Ordering::Equal);
+        assert_eq!(
+            compare_versions("v1.9.7-rc1", "v1.9.7-rc1"),
+            Ordering::Equal
+        );
+        assert_eq!(compare_versions("1.9.7", "1-9-7"), Ordering::Equal);
+    }
+}
@@ -0,0 +1,41 @@
+use crate::util::accessor::*;
+use crate::util::file_name::decode_file_name;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A GenieACS virtual parameter script, as stored under
+/// `/virtual_parameters`. Devices expose its value as
+/// `VirtualParameters.<name>`.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[repr(C)]
+pub struct AcsVirtualParameter {
+    #[serde(default = "unset_str", rename = "_id")]
+    pub name: String,
+
+    /// JavaScript source computing (and optionally writing) the value
+    #[serde(default = "unset_str")]
+    pub script: String,
+}
+
+impl AcsVirtualParameter {
+    pub fn new(name: &str, script: &str) -> Self {
+        return AcsVirtualParameter {
+            name: name.to_string(),
+            script: script.to_string(),
+        };
+    }
+
+    /// Reads a virtual parameter from a script file named after it, e.g.
+    /// `virtual_parameters/wan_ip.js` for `wan_ip`. The name is
+    /// percent-decoded as in configuration snapshots.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let name = decode_file_name(path)?;
+        let script = std::fs::read_to_string(path)?;
+        return Ok(AcsVirtualParameter::new(&name, &script));
+    }
+
+    /// Path of the parameter on devices.
+    pub fn parameter_path(&self) -> String {
+        return format!("VirtualParameters.{}", self.name);
+    }
+}
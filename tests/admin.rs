@@ -0,0 +1,239 @@
+//! ACS administration resources against the mock NBI.
+
+mod common;
+
+use acs_api_rs::config_snapshot::{AcsConfigSnapshot, ChangeKind, ResourceKind};
+use acs_api_rs::mock::MockNbi;
+use acs_api_rs::preset::{AcsPreset, PresetConfiguration};
+use acs_api_rs::provision::AcsProvision;
+use acs_api_rs::virtual_parameter::AcsVirtualParameter;
+use common::*;
+use reqwest::Method;
+use serde_json::json;
+
+fn lab_preset() -> AcsPreset {
+    let mut preset = AcsPreset::new("lab");
+    preset.weight = 10;
+    preset.precondition = r#"{"_tags":"lab"}"#.to_string();
+    preset.configurations = vec![
+        AcsProvision::new("inform", "").call(vec![json!(300)]),
+        PresetConfiguration::AddTag {
+            tag: "managed".to_string(),
+        },
+    ];
+    return preset;
+}
+
+#[test]
+fn preset_crud() {
+    let nbi = MockNbi::new();
+    let conn = nbi.connection();
+
+    conn.put_preset(&lab_preset()).unwrap();
+    assert_eq!(conn.get_preset("lab").unwrap(), Some(lab_preset()));
+    assert_eq!(conn.list_presets().unwrap(), vec![lab_preset()]);
+
+    conn.delete_preset("lab").unwrap();
+    assert_eq!(conn.get_preset("lab").unwrap(), None);
+}
+
+#[test]
+fn preset_keeps_unknown_configurations() {
+    let nbi = MockNbi::new();
+    let future = json!({"type": "future_kind", "name": "Device.X", "extra": [1, 2]});
+    nbi.put_object(
+        "presets",
+        "future",
+        json!({"configurations": [{"type": "add_tag", "tag": "t"}, future]}),
+    );
+    let conn = nbi.connection();
+
+    let preset = conn.get_preset("future").unwrap().unwrap();
+    assert_eq!(
+        preset.configurations[1],
+        PresetConfiguration::Raw(future.clone())
+    );
+
+    conn.put_preset(&preset).unwrap();
+    assert_eq!(
+        nbi.object("presets", "future").unwrap()["configurations"][1],
+        future
+    );
+}
+
+#[test]
+fn provision_scripts_load_from_percent_encoded_files() {
+    let nbi = MockNbi::new();
+    let conn = nbi.connection();
+    let dir = temp_dir("provisions");
+    let path = dir.join("inform%2Fwan.js");
+    std::fs::write(&path, "declare(\"Device.DeviceInfo.UpTime\", {value: 1});").unwrap();
+
+    let provision = AcsProvision::load(&path).unwrap();
+    assert_eq!(provision.name, "inform/wan");
+    conn.put_provision(&provision).unwrap();
+
+    assert_eq!(conn.get_provision("inform/wan").unwrap(), Some(provision));
+    conn.delete_provision("inform/wan").unwrap();
+    assert!(conn.list_provisions().unwrap().is_empty());
+}
+
+#[test]
+fn virtual_parameter_crud_and_device_values() {
+    let nbi = MockNbi::new();
+    let conn = nbi.connection();
+    let dir = temp_dir("virtual-parameters");
+    let path = dir.join("wan_ip.js");
+    std::fs::write(
+        &path,
+        "return {writable: false, value: [\"\", \"xsd:string\"]};",
+    )
+    .unwrap();
+
+    let virtual_parameter = AcsVirtualParameter::load(&path).unwrap();
+    assert_eq!(
+        virtual_parameter.parameter_path(),
+        "VirtualParameters.wan_ip"
+    );
+    conn.put_virtual_parameter(&virtual_parameter).unwrap();
+    assert_eq!(
+        conn.get_virtual_parameter("wan_ip").unwrap(),
+        Some(virtual_parameter)
+    );
+
+    let mut device = tr181_device("0001");
+    device["VirtualParameters"] = json!({
+        "wan_ip": {"_value": "192.0.2.1", "_type": "xsd:string", "_writable": false},
+    });
+    let id = nbi.add_device(device).unwrap();
+    let values = conn.get_device_virtual_parameters(id).unwrap();
+    assert_eq!(values.get_node("wan_ip").unwrap().value, "192.0.2.1");
+
+    conn.delete_virtual_parameter("wan_ip").unwrap();
+    assert!(conn.list_virtual_parameters().unwrap().is_empty());
+}
+
+/// A mock ACS with one of each resource.
+fn populated() -> MockNbi {
+    let nbi = MockNbi::new();
+    let conn = nbi.connection();
+    conn.put_provision(&AcsProvision::new("inform", "log(\"inform\");"))
+        .unwrap();
+    conn.put_virtual_parameter(&AcsVirtualParameter::new("wan_ip", "return {};"))
+        .unwrap();
+    conn.put_preset(&lab_preset()).unwrap();
+    nbi.put_object("config", "cwmp.downloadTimeout", json!({"value": 3600}));
+    nbi.add_file(
+        "fw-1.0.bin",
+        json!({"fileType": "1 Firmware Upgrade Image", "version": "1.0"}),
+        b"image".to_vec(),
+    );
+    return nbi;
+}
+
+#[test]
+fn config_snapshot_round_trips_through_a_directory() {
+    let conn = populated().connection();
+    let dir = temp_dir("snapshot");
+
+    let snapshot = conn.export_config().unwrap();
+    snapshot.write_dir(&dir).unwrap();
+
+    assert!(dir.join("presets/lab.json").exists());
+    assert!(dir.join("files/fw-1.0.bin.json").exists());
+    assert_eq!(AcsConfigSnapshot::read_dir(&dir).unwrap(), snapshot);
+    assert!(conn.plan_config(&snapshot, true, true).unwrap().is_empty());
+}
+
+#[test]
+fn config_apply_creates_updates_and_prunes() {
+    let source = populated().connection();
+    let mut desired = source.export_config().unwrap();
+    desired.provisions.get_mut("inform").unwrap().script = "log(\"v2\");".to_string();
+    desired.presets.remove("lab");
+
+    let target = populated();
+    let conn = target.connection();
+    conn.put_provision(&AcsProvision::new("extra", "")).unwrap();
+
+    // Without pruning only the changed provision is applied
+    let plan = conn.plan_config(&desired, false, false).unwrap();
+    assert_eq!(plan.changes.len(), 1);
+    assert_eq!(plan.changes[0].resource, ResourceKind::Provision);
+    assert_eq!(plan.changes[0].change, ChangeKind::Update);
+
+    let plan = conn.plan_config(&desired, true, false).unwrap();
+    let deleted: Vec<&str> = plan
+        .changes
+        .iter()
+        .filter(|c| c.change == ChangeKind::Delete)
+        .map(|c| c.name.as_str())
+        .collect();
+    // Presets go before the scripts they may reference
+    assert_eq!(deleted, vec!["lab", "extra"]);
+    conn.apply_config(&desired, &plan).unwrap();
+
+    assert_eq!(
+        conn.get_provision("inform").unwrap().unwrap().script,
+        "log(\"v2\");"
+    );
+    assert_eq!(conn.get_preset("lab").unwrap(), None);
+    assert_eq!(conn.get_provision("extra").unwrap(), None);
+    assert!(conn.plan_config(&desired, true, false).unwrap().is_empty());
+}
+
+#[test]
+fn config_plan_compares_file_metadata_only() {
+    let nbi = populated();
+    let conn = nbi.connection();
+    let mut desired = conn.export_config().unwrap();
+
+    // GridFS bookkeeping differs between uploads of the same file
+    let file = desired.files.get_mut("fw-1.0.bin").unwrap();
+    file["uploadDate"] = json!("2021-01-01T00:00:00.000Z");
+    file["chunkSize"] = json!(261120);
+    assert!(conn.plan_config(&desired, true, true).unwrap().is_empty());
+
+    desired.files.get_mut("fw-1.0.bin").unwrap()["metadata"]["version"] = json!("1.1");
+    let plan = conn.plan_config(&desired, true, true).unwrap();
+    assert!(plan.is_empty());
+    assert_eq!(plan.warnings.len(), 1);
+}
+
+#[test]
+fn config_plan_prunes_files_only_when_asked() {
+    let nbi = populated();
+    let conn = nbi.connection();
+    let mut desired = conn.export_config().unwrap();
+    desired.files.clear();
+
+    assert!(conn.plan_config(&desired, true, false).unwrap().is_empty());
+
+    let plan = conn.plan_config(&desired, false, true).unwrap();
+    assert_eq!(plan.changes.len(), 1);
+    assert_eq!(plan.changes[0].resource, ResourceKind::File);
+    conn.apply_config(&desired, &plan).unwrap();
+    assert!(nbi.files().is_empty());
+}
+
+#[test]
+fn config_entries_are_reported_not_written() {
+    let nbi = populated();
+    let conn = nbi.connection();
+    let mut desired = conn.export_config().unwrap();
+    desired
+        .config
+        .insert("cwmp.downloadTimeout".to_string(), json!(60));
+
+    let plan = conn.plan_config(&desired, true, true).unwrap();
+    assert!(plan.is_empty());
+    assert_eq!(plan.warnings.len(), 1);
+    conn.apply_config(&desired, &plan).unwrap();
+
+    let writes = nbi
+        .requests()
+        .iter()
+        .filter(|r| r.path.starts_with("/config") && r.method != Method::GET)
+        .count();
+    assert_eq!(writes, 0);
+}
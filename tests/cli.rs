@@ -0,0 +1,159 @@
+//! The `acs` binary against the mock NBI served over HTTP.
+
+mod common;
+
+use acs_api_rs::mock::server::MockServer;
+use acs_api_rs::mock::MockNbi;
+use common::*;
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Serves `nbi` on a free local port.
+fn serve(nbi: &MockNbi) -> MockServer {
+    return nbi.serve("127.0.0.1:0").unwrap();
+}
+
+/// Runs `acs` against `server` with `stdin` as its input, away from the
+/// user's config file and shell history.
+fn acs(server: &MockServer, args: &[&str], stdin: &str) -> Output {
+    let home = temp_dir(&format!("cli-home-{}", RUNS.fetch_add(1, Ordering::SeqCst)));
+    let mut child = Command::new(env!("CARGO_BIN_EXE_acs"))
+        .args(["--url", &server.addr])
+        .args(args)
+        .env_clear()
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    return child.wait_with_output().unwrap();
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    return String::from_utf8(output.stdout.clone()).unwrap();
+}
+
+#[test]
+fn lists_devices_as_json() {
+    let (nbi, _conn, id) = router();
+    let server = serve(&nbi);
+
+    let output = acs(&server, &["-o", "json", "devices"], "");
+
+    let devices: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(devices.as_array().unwrap().len(), 1);
+    assert_eq!(devices[0]["_id"], id.as_str());
+}
+
+#[test]
+fn sets_parameters_through_selectors() {
+    let (nbi, conn, id) = router();
+    let server = serve(&nbi);
+
+    let output = acs(
+        &server,
+        &["set", &id, "Device.WiFi.SSID.[cpe-guest].SSID=visitors"],
+        "",
+    );
+    stdout(&output);
+
+    let tree = conn
+        .get_parameter_values(id, vec!["Device.WiFi.SSID.2.SSID".to_string()])
+        .unwrap();
+    assert_eq!(
+        tree.get_node("Device.WiFi.SSID.2.SSID").unwrap().value,
+        "visitors"
+    );
+}
+
+#[test]
+fn fetches_files_to_standard_output() {
+    let nbi = MockNbi::new();
+    nbi.add_file(
+        "fw.bin",
+        serde_json::json!({"fileType": "1 Firmware Upgrade Image"}),
+        b"image".to_vec(),
+    );
+    let server = serve(&nbi);
+
+    let output = acs(
+        &server,
+        &["--file-server", &server.file_server_addr, "fetch", "fw.bin"],
+        "",
+    );
+
+    assert_eq!(stdout(&output), "image");
+}
+
+#[test]
+fn shell_browses_tr098_devices() {
+    let nbi = MockNbi::new();
+    let id = add_cpe(&nbi, tr098_device("0002"));
+    let server = serve(&nbi);
+
+    let output = acs(
+        &server,
+        &["shell", &id],
+        "pwd\ncd DeviceInfo\npwd\nls\nexit\n",
+    );
+
+    let out = stdout(&output);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[0], "InternetGatewayDevice");
+    assert_eq!(lines[1], "InternetGatewayDevice.DeviceInfo");
+    assert!(lines[3].starts_with("SoftwareVersion  2.0.0"), "{}", out);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn config_apply_fails_while_config_entries_differ() {
+    let (nbi, conn, _id) = router();
+    nbi.put_object(
+        "config",
+        "cwmp.downloadTimeout",
+        serde_json::json!({"value": 3600}),
+    );
+    let server = serve(&nbi);
+    let mut desired = conn.export_config().unwrap();
+    desired.config.insert(
+        "cwmp.downloadTimeout".to_string(),
+        serde_json::json!({"value": 60}),
+    );
+    let dir = temp_dir("cli-config");
+    desired.write_dir(&dir).unwrap();
+    let dir = dir.to_str().unwrap();
+
+    let output = acs(&server, &["config", "apply", dir], "");
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("! Config cwmp.downloadTimeout differs"),
+        "{}",
+        stdout
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--allow-warnings"));
+
+    let output = acs(&server, &["config", "apply", "--allow-warnings", dir], "");
+    assert!(output.status.success());
+    assert_eq!(
+        nbi.object("config", "cwmp.downloadTimeout").unwrap()["value"],
+        3600
+    );
+}
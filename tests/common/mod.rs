@@ -0,0 +1,117 @@
+//! Devices and helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use acs_api_rs::connection::AcsConnection;
+use acs_api_rs::mock::simulator::SimulatedCpe;
+use acs_api_rs::mock::MockNbi;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+/// Timestamp old enough for every parameter to count as stale
+pub const OLD_TIMESTAMP: &str = "2020-01-01T00:00:00.000Z";
+
+fn parameter(value: Value, value_type: &str, writable: bool) -> Value {
+    return json!({
+        "_value": value,
+        "_type": value_type,
+        "_writable": writable,
+        "_timestamp": OLD_TIMESTAMP,
+    });
+}
+
+fn ssid(alias: &str, name: &str, mac: &str) -> Value {
+    return json!({
+        "_object": true,
+        "_writable": true,
+        "Alias": parameter(json!(alias), "xsd:string", true),
+        "SSID": parameter(json!(name), "xsd:string", true),
+        "MACAddress": parameter(json!(mac), "xsd:string", false),
+    });
+}
+
+/// GenieACS document of a TR-181 router with two SSIDs and a
+/// `ManagementServer.Password`.
+pub fn tr181_device(serial: &str) -> Value {
+    return json!({
+        "_deviceId": {
+            "_Manufacturer": "Acme",
+            "_OUI": "001122",
+            "_ProductClass": "Router",
+            "_SerialNumber": serial,
+        },
+        "Device": {
+            "_object": true,
+            "_writable": false,
+            "DeviceInfo": {
+                "_object": true,
+                "_writable": false,
+                "SoftwareVersion": parameter(json!("1.0.0"), "xsd:string", false),
+                "UpTime": parameter(json!(100), "xsd:unsignedInt", false),
+            },
+            "ManagementServer": {
+                "_object": true,
+                "_writable": false,
+                "PeriodicInformInterval": parameter(json!(300), "xsd:unsignedInt", true),
+                "Password": parameter(json!("hunter2"), "xsd:string", true),
+            },
+            "WiFi": {
+                "_object": true,
+                "_writable": false,
+                "SSID": {
+                    "_object": true,
+                    "_writable": true,
+                    "1": ssid("cpe-main", "home", "AA:BB:CC:DD:EE:01"),
+                    "2": ssid("cpe-guest", "guest", "AA:BB:CC:DD:EE:02"),
+                },
+            },
+        },
+    });
+}
+
+/// GenieACS document of a TR-098 gateway.
+pub fn tr098_device(serial: &str) -> Value {
+    return json!({
+        "_deviceId": {
+            "_Manufacturer": "Acme",
+            "_OUI": "001122",
+            "_ProductClass": "Gateway",
+            "_SerialNumber": serial,
+        },
+        "InternetGatewayDevice": {
+            "_object": true,
+            "_writable": false,
+            "DeviceInfo": {
+                "_object": true,
+                "_writable": false,
+                "SoftwareVersion": parameter(json!("2.0.0"), "xsd:string", false),
+                "UpTime": parameter(json!(100), "xsd:unsignedInt", false),
+            },
+        },
+    });
+}
+
+/// Adds `doc` to `nbi` with a simulated CPE executing its tasks.
+pub fn add_cpe(nbi: &MockNbi, doc: Value) -> String {
+    let cpe = SimulatedCpe::from_device_doc(&doc);
+    let id = nbi.add_device(doc).unwrap();
+    nbi.attach_cpe(&id, cpe).unwrap();
+    return id;
+}
+
+/// A mock NBI with one TR-181 CPE, a connection to it and the device id.
+pub fn router() -> (MockNbi, AcsConnection, String) {
+    let nbi = MockNbi::new();
+    let id = add_cpe(&nbi, tr181_device("0001"));
+    let conn = nbi.connection();
+    return (nbi, conn, id);
+}
+
+/// Empty directory for a test, removed first if left over from a
+/// previous run.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("acs-api-rs-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    return dir;
+}
@@ -0,0 +1,269 @@
+//! Device tasks against simulated CPEs.
+
+mod common;
+
+use acs_api_rs::bulk::{BulkOptions, BulkTarget};
+use acs_api_rs::mock::simulator::SimulatedCpe;
+use acs_api_rs::mock::MockNbi;
+use acs_api_rs::parameter_value::ParameterValue;
+use acs_api_rs::util::timestamp::parse_timestamp;
+use common::*;
+use std::time::{Duration, SystemTime};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+fn value(conn: &acs_api_rs::connection::AcsConnection, id: &str, path: &str) -> String {
+    let tree = conn
+        .get_parameter_values(id.to_string(), vec![path.to_string()])
+        .unwrap();
+    return tree.get_node(path).unwrap().value.clone();
+}
+
+#[test]
+fn refresh_parameter_values_refreshes_stale_parameters() {
+    let (_nbi, conn, id) = router();
+    let path = "Device.DeviceInfo.SoftwareVersion";
+
+    let tree = conn
+        .refresh_parameter_values(id, vec![path.to_string()], Duration::from_secs(3600), WAIT)
+        .unwrap();
+
+    let refreshed = parse_timestamp(&tree.get_node(path).unwrap().timestamp).unwrap();
+    let age = SystemTime::now().duration_since(refreshed).unwrap();
+    assert!(age < Duration::from_secs(60));
+}
+
+#[test]
+fn refresh_of_offline_device_fails_without_leaving_tasks() {
+    let (nbi, conn, id) = router();
+    nbi.set_online(&id, false);
+
+    let result = conn.refresh_parameter_values(
+        id,
+        vec!["Device.DeviceInfo".to_string()],
+        Duration::ZERO,
+        WAIT,
+    );
+
+    assert!(result.is_err());
+    assert!(nbi.tasks().is_empty());
+}
+
+#[test]
+fn refresh_object_queues_task_for_offline_device() {
+    let (nbi, conn, id) = router();
+    nbi.set_online(&id, false);
+
+    conn.refresh_object(id.clone(), "Device.DeviceInfo")
+        .unwrap();
+
+    let tasks = conn.list_tasks(&id).unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].name, "refreshObject");
+    assert_eq!(nbi.tasks().len(), 1);
+}
+
+#[test]
+fn add_object_returns_new_instance() {
+    let (nbi, conn, id) = router();
+
+    let instance = conn
+        .add_object(
+            id.clone(),
+            "Device.WiFi.SSID".to_string(),
+            vec![ParameterValue::new("SSID", "lab", "xsd:string")],
+            WAIT,
+        )
+        .unwrap();
+
+    assert_eq!(instance, "Device.WiFi.SSID.3");
+    let cpe = nbi.cpe(&id).unwrap();
+    assert_eq!(
+        cpe.model.get_node("Device.WiFi.SSID.3.SSID").unwrap().value,
+        "lab"
+    );
+}
+
+#[test]
+fn delete_object_removes_instance() {
+    let (nbi, conn, id) = router();
+
+    conn.add_del_object(id.clone(), false, "Device.WiFi.SSID.2".to_string())
+        .unwrap();
+
+    let cpe = nbi.cpe(&id).unwrap();
+    assert!(cpe.model.get_node("Device.WiFi.SSID.2").is_none());
+    assert!(cpe.model.get_node("Device.WiFi.SSID.1").is_some());
+}
+
+#[test]
+fn resolves_alias_and_key_selectors() {
+    let (_nbi, conn, id) = router();
+
+    let alias = conn
+        .resolve_path(id.clone(), "Device.WiFi.SSID.[cpe-guest].SSID")
+        .unwrap();
+    assert_eq!(alias, "Device.WiFi.SSID.2.SSID");
+
+    // Selector values match case-insensitively
+    let key = conn
+        .resolve_path(
+            id.clone(),
+            "Device.WiFi.SSID.[MACAddress=\"aa:bb:cc:dd:ee:01\"]",
+        )
+        .unwrap();
+    assert_eq!(key, "Device.WiFi.SSID.1");
+
+    assert!(conn
+        .resolve_path(id, "Device.WiFi.SSID.[cpe-missing].SSID")
+        .is_err());
+}
+
+#[test]
+fn set_parameter_values_resolves_selectors() {
+    let (_nbi, conn, id) = router();
+
+    conn.set_parameter_values(
+        id.clone(),
+        vec![ParameterValue::new(
+            "Device.WiFi.SSID.[cpe-guest].SSID",
+            "visitors",
+            "xsd:string",
+        )],
+    )
+    .unwrap();
+
+    assert_eq!(value(&conn, &id, "Device.WiFi.SSID.2.SSID"), "visitors");
+}
+
+#[test]
+fn read_only_parameter_faults() {
+    let (nbi, conn, id) = router();
+
+    // The NBI answers 202 for the faulted task, which stays queued
+    conn.set_parameter_values(
+        id.clone(),
+        vec![ParameterValue::new(
+            "Device.DeviceInfo.SoftwareVersion",
+            "9.9.9",
+            "xsd:string",
+        )],
+    )
+    .unwrap();
+
+    let faults = conn.list_faults(&id).unwrap();
+    assert_eq!(faults.len(), 1);
+    assert_eq!(faults[0].code, "cwmp.9008");
+    assert_eq!(nbi.tasks().len(), 1);
+    assert_eq!(
+        value(&conn, &id, "Device.DeviceInfo.SoftwareVersion"),
+        "1.0.0"
+    );
+}
+
+#[test]
+fn reboot_and_factory_reset_reach_the_cpe() {
+    let (nbi, conn, id) = router();
+
+    conn.set_parameter_values(
+        id.clone(),
+        vec![ParameterValue::new(
+            "Device.ManagementServer.PeriodicInformInterval",
+            "60",
+            "xsd:unsignedInt",
+        )],
+    )
+    .unwrap();
+    conn.reboot(id.clone()).unwrap();
+    let mut cpe = nbi.cpe(&id).unwrap();
+    assert_eq!(cpe.boot_count, 1);
+    assert!(cpe.take_events().contains(&"M Reboot".to_string()));
+
+    conn.factory_reset(id.clone()).unwrap();
+    assert_eq!(
+        value(&conn, &id, "Device.ManagementServer.PeriodicInformInterval"),
+        "300"
+    );
+}
+
+#[test]
+fn simulator_loads_tr098_snapshots() {
+    let nbi = MockNbi::new();
+    let id = add_cpe(&nbi, tr098_device("0002"));
+    let conn = nbi.connection();
+
+    let tree = conn
+        .get_parameter_values(
+            id.clone(),
+            vec!["Device".to_string(), "InternetGatewayDevice".to_string()],
+        )
+        .unwrap();
+    assert_eq!(tree.data_model_root(), Some("InternetGatewayDevice"));
+
+    let snapshot = serde_json::to_string(&tree).unwrap();
+    let cpe = SimulatedCpe::from_snapshot(&snapshot).unwrap();
+    assert_eq!(cpe.root_name(), "InternetGatewayDevice");
+    assert!(cpe
+        .model
+        .get_node("InternetGatewayDevice._object")
+        .is_none());
+}
+
+#[test]
+fn bulk_operations_report_per_device() {
+    let nbi = MockNbi::new();
+    let first = add_cpe(&nbi, tr181_device("0001"));
+    let second = add_cpe(&nbi, tr181_device("0002"));
+    let conn = nbi.connection();
+    for id in [&first, &second] {
+        conn.add_del_tag(id.clone(), true, "lab".to_string())
+            .unwrap();
+    }
+
+    let options = BulkOptions {
+        concurrency: 2,
+        min_interval: None,
+    };
+    let report = conn
+        .bulk_reboot(
+            &BulkTarget::Query(r#"{"_tags":"lab"}"#.to_string()),
+            &options,
+        )
+        .unwrap();
+    assert!(report.is_success());
+    assert_eq!(report.results.len(), 2);
+    for id in [&first, &second] {
+        assert_eq!(nbi.cpe(id).unwrap().boot_count, 1);
+    }
+
+    let target = BulkTarget::Devices(vec![first.clone(), "missing".to_string()]);
+    let report = conn.bulk_reboot(&target, &options).unwrap();
+    assert_eq!(report.succeeded().len(), 1);
+    assert_eq!(report.failed()[0].device_id, "missing");
+}
+
+#[test]
+fn connection_is_shared_across_threads() {
+    let nbi = MockNbi::new();
+    let ids: Vec<String> = (0..4)
+        .map(|n| add_cpe(&nbi, tr181_device(&format!("{:04}", n))))
+        .collect();
+    let conn = nbi.connection();
+
+    let handles: Vec<_> = ids
+        .iter()
+        .map(|id| {
+            let conn = conn.clone();
+            let id = id.clone();
+            std::thread::spawn(move || {
+                conn.get_parameter_values(id, vec!["Device.DeviceInfo".to_string()])
+                    .map(|tree| tree.get_node("Device.DeviceInfo.SoftwareVersion").is_some())
+                    .unwrap_or(false)
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        assert!(handle.join().unwrap());
+    }
+}
@@ -0,0 +1,278 @@
+//! ACS files against the mock NBI.
+
+mod common;
+
+use acs_api_rs::bulk::{BulkOptions, BulkTarget};
+use acs_api_rs::file::{AcsFileMetadata, FileType, TransferOptions};
+use acs_api_rs::mock::MockNbi;
+use acs_api_rs::request::download_command::DownloadCommand;
+use common::*;
+use reqwest::Method;
+use serde_json::json;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+const MD5_OF_IMAGE: &str = "78805a221a988e79ef3f42d7c5bfd418";
+
+fn firmware(version: &str) -> AcsFileMetadata {
+    return AcsFileMetadata::new(FileType::FirmwareUpgradeImage)
+        .oui("001122")
+        .product_class("Router")
+        .version(version);
+}
+
+fn upload(nbi: &MockNbi, name: &str, version: &str) {
+    nbi.connection()
+        .upload(
+            name,
+            Cursor::new(b"image".to_vec()),
+            Some(5),
+            &firmware(version),
+            &TransferOptions::default(),
+        )
+        .unwrap();
+}
+
+#[test]
+fn lists_files_and_metadata() {
+    let nbi = MockNbi::new();
+    upload(&nbi, "fw-1.0.bin", "1.0");
+    nbi.add_file(
+        "config.xml",
+        json!({"fileType": "3 Vendor Configuration File"}),
+        b"<x/>".to_vec(),
+    );
+    let conn = nbi.connection();
+
+    assert_eq!(conn.list_files().unwrap().len(), 2);
+    let file = conn.get_file("fw-1.0.bin").unwrap().unwrap();
+    assert_eq!(file.length, 5);
+    assert_eq!(file.md5, MD5_OF_IMAGE);
+    assert_eq!(file.metadata, firmware("1.0"));
+    assert_eq!(
+        conn.get_file("config.xml")
+            .unwrap()
+            .unwrap()
+            .metadata
+            .file_type,
+        FileType::VendorConfigurationFile
+    );
+    assert!(conn.get_file("missing").unwrap().is_none());
+}
+
+#[test]
+fn latest_firmware_compares_versions() {
+    let nbi = MockNbi::new();
+    upload(&nbi, "fw-1.9.7.bin", "1.9.7");
+    upload(&nbi, "fw-1.10.0.bin", "1.10.0");
+    upload(&nbi, "fw-1.10.1-rc1.bin", "1.10.1-rc1");
+    upload(&nbi, "fw-1.10.1.bin", "1.10.1");
+    let id = nbi.add_device(tr181_device("0001")).unwrap();
+    let conn = nbi.connection();
+
+    let latest = conn.latest_firmware("001122", "Router").unwrap().unwrap();
+    assert_eq!(latest.name, "fw-1.10.1.bin");
+    assert_eq!(
+        conn.latest_firmware_for_device(&id).unwrap().unwrap().name,
+        "fw-1.10.1.bin"
+    );
+    assert!(conn.latest_firmware("001122", "Other").unwrap().is_none());
+}
+
+#[test]
+fn upload_reports_digests_and_progress() {
+    let nbi = MockNbi::new();
+    let conn = nbi.connection();
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let seen = progress.clone();
+    let options = TransferOptions::default()
+        .expected_md5(MD5_OF_IMAGE)
+        .progress(move |done, total| seen.lock().unwrap().push((done, total)));
+
+    let report = conn
+        .upload(
+            "fw.bin",
+            Cursor::new(b"image".to_vec()),
+            Some(5),
+            &firmware("1.0"),
+            &options,
+        )
+        .unwrap();
+
+    assert_eq!(report.length, 5);
+    assert_eq!(report.md5, MD5_OF_IMAGE);
+    assert_eq!(progress.lock().unwrap().last(), Some(&(5, Some(5))));
+    assert_eq!(nbi.file_content("fw.bin").unwrap(), b"image");
+}
+
+#[test]
+fn upload_mismatch_deletes_the_stored_file() {
+    let nbi = MockNbi::new();
+    let conn = nbi.connection();
+
+    let result = conn.upload(
+        "fw.bin",
+        Cursor::new(b"image".to_vec()),
+        Some(5),
+        &firmware("1.0"),
+        &TransferOptions::default().expected_md5("00000000000000000000000000000000"),
+    );
+
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("stored file was deleted"), "{}", error);
+    assert!(error.contains("does not match expected"), "{}", error);
+    assert!(nbi.file_content("fw.bin").is_none());
+    assert!(conn.latest_firmware("001122", "Router").unwrap().is_none());
+}
+
+#[test]
+fn upload_mismatch_keeps_the_stored_file_when_asked() {
+    let nbi = MockNbi::new();
+    let conn = nbi.connection();
+
+    let result = conn.upload(
+        "fw.bin",
+        Cursor::new(b"image".to_vec()),
+        Some(5),
+        &firmware("1.0"),
+        &TransferOptions::default()
+            .expected_sha256("00")
+            .keep_unverified(true),
+    );
+
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("stored file was kept"), "{}", error);
+    assert_eq!(nbi.file_content("fw.bin").unwrap(), b"image");
+    assert!(nbi.requests().iter().all(|r| r.method != Method::DELETE));
+}
+
+#[test]
+fn fetches_from_the_file_server() {
+    let nbi = MockNbi::new();
+    upload(&nbi, "fw 1.0.bin", "1.0");
+    let conn = nbi.connection();
+    let dir = temp_dir("fetch");
+    let path = dir.join("fw.bin");
+
+    let report = conn
+        .fetch_file_to_path(
+            "fw 1.0.bin",
+            &path,
+            &TransferOptions::default().expected_md5(MD5_OF_IMAGE),
+        )
+        .unwrap();
+
+    assert_eq!(report.md5, MD5_OF_IMAGE);
+    assert_eq!(std::fs::read(&path).unwrap(), b"image");
+    let last = nbi.requests().pop().unwrap();
+    assert_eq!(
+        (last.method, last.path.as_str()),
+        (Method::GET, "/fw%201.0.bin")
+    );
+}
+
+#[test]
+fn failed_fetch_leaves_no_file() {
+    let nbi = MockNbi::new();
+    upload(&nbi, "fw.bin", "1.0");
+    let conn = nbi.connection();
+    let dir = temp_dir("fetch-mismatch");
+    let path = dir.join("fw.bin");
+
+    let result = conn.fetch_file_to_path(
+        "fw.bin",
+        &path,
+        &TransferOptions::default().expected_sha256("00"),
+    );
+
+    assert!(result.is_err());
+    assert!(std::fs::read_dir(&dir).unwrap().next().is_none());
+    assert!(conn
+        .fetch_file("missing", &mut Vec::new(), &TransferOptions::default())
+        .is_err());
+}
+
+#[test]
+fn delete_file_encodes_the_name() {
+    let nbi = MockNbi::new();
+    upload(&nbi, "fw 1.0/a.bin", "1.0");
+    let conn = nbi.connection();
+
+    conn.delete_file("fw 1.0/a.bin").unwrap();
+
+    assert!(nbi.files().is_empty());
+}
+
+#[test]
+fn download_sends_typed_options() {
+    let (nbi, conn, id) = router();
+    upload(&nbi, "fw-2.0.bin", "2.0.0");
+
+    let file = conn.latest_firmware_for_device(&id).unwrap().unwrap();
+    conn.download(
+        id.clone(),
+        DownloadCommand::for_file(&file).target_file_name("firmware.img"),
+    )
+    .unwrap();
+
+    let cpe = nbi.cpe(&id).unwrap();
+    assert_eq!(cpe.downloads.len(), 1);
+    assert_eq!(cpe.downloads[0].file_type, "1 Firmware Upgrade Image");
+    assert_eq!(cpe.downloads[0].target_file_name, "firmware.img");
+    assert_eq!(
+        cpe.model
+            .get_node("Device.DeviceInfo.SoftwareVersion")
+            .unwrap()
+            .value,
+        "2.0.0"
+    );
+
+    let task: serde_json::Value =
+        serde_json::from_slice(&nbi.requests().pop().unwrap().body).unwrap();
+    assert_eq!(task["fileName"], "fw-2.0.bin");
+    assert_eq!(task["targetFileName"], "firmware.img");
+}
+
+#[test]
+fn download_by_name_uses_file_metadata() {
+    let (nbi, conn, id) = router();
+    nbi.add_file(
+        "settings.xml",
+        json!({"fileType": "3 Vendor Configuration File"}),
+        b"<x/>".to_vec(),
+    );
+
+    conn.download(id.clone(), "settings.xml").unwrap();
+
+    let cpe = nbi.cpe(&id).unwrap();
+    assert_eq!(cpe.downloads[0].file_type, "3 Vendor Configuration File");
+    assert_eq!(cpe.boot_count, 0);
+}
+
+#[test]
+fn bulk_download_sends_typed_options_to_every_device() {
+    let nbi = MockNbi::new();
+    let ids = vec![
+        add_cpe(&nbi, tr181_device("0001")),
+        add_cpe(&nbi, tr181_device("0002")),
+    ];
+    upload(&nbi, "fw-2.0.bin", "2.0.0");
+    let conn = nbi.connection();
+
+    let command = DownloadCommand::new("fw-2.0.bin")
+        .file_type(FileType::FirmwareUpgradeImage)
+        .target_file_name("firmware.img");
+    let report = conn
+        .bulk_download(
+            &BulkTarget::Devices(ids.clone()),
+            &BulkOptions::default(),
+            command,
+        )
+        .unwrap();
+
+    assert!(report.is_success());
+    for id in &ids {
+        let cpe = nbi.cpe(id).unwrap();
+        assert_eq!(cpe.downloads[0].target_file_name, "firmware.img");
+    }
+}
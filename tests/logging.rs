@@ -0,0 +1,163 @@
+//! Tracing spans and events emitted by `AcsConnection`.
+
+mod common;
+
+use acs_api_rs::redact::{Redactor, REDACTED};
+use common::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Formats the fields of a span or event as `name=value` pairs.
+#[derive(Default)]
+struct Fields(Vec<String>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.push(format!("{}={:?}", field.name(), value));
+    }
+}
+
+/// Subscriber remembering every span and event with its fields.
+#[derive(Default)]
+struct Capture {
+    spans: Mutex<Vec<String>>,
+    events: Mutex<Vec<(Level, String)>>,
+    next_id: AtomicU64,
+}
+
+impl Capture {
+    fn spans(&self) -> Vec<String> {
+        return self.spans.lock().unwrap().clone();
+    }
+
+    fn events(&self) -> Vec<(Level, String)> {
+        return self.events.lock().unwrap().clone();
+    }
+}
+
+impl Subscriber for Capture {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        return true;
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        self.spans.lock().unwrap().push(format!(
+            "{} {}",
+            span.metadata().name(),
+            fields.0.join(" ")
+        ));
+        return Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.events
+            .lock()
+            .unwrap()
+            .push((*event.metadata().level(), fields.0.join(" ")));
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+/// Runs `f` with a fresh `Capture` as the thread's subscriber.
+fn capture(f: impl FnOnce()) -> Arc<Capture> {
+    let capture = Arc::new(Capture::default());
+    tracing::subscriber::with_default(capture.clone(), f);
+    return capture;
+}
+
+#[test]
+fn operations_emit_spans_and_events() {
+    let (_nbi, conn, id) = router();
+
+    let capture = capture(|| conn.reboot(id.clone()).unwrap());
+
+    let spans = capture.spans();
+    assert!(spans[0].starts_with("acs.reboot"), "{:?}", spans);
+    assert!(spans[0].contains(&id), "{:?}", spans);
+    assert!(spans[1].starts_with("acs.http method=POST"), "{:?}", spans);
+    assert!(capture
+        .events()
+        .iter()
+        .any(|(level, fields)| *level == Level::DEBUG && fields.contains("\"reboot\"")));
+}
+
+#[test]
+fn parameter_traces_use_the_connection_redactor() {
+    let (_nbi, mut conn, id) = router();
+    conn.redactor = Some(Redactor::new(vec![
+        "Device.ManagementServer.PeriodicInformInterval".to_string(),
+        "*.Password".to_string(),
+    ]));
+
+    let capture = capture(|| {
+        conn.get_parameter_values(id.clone(), vec!["Device.ManagementServer".to_string()])
+            .unwrap();
+    });
+
+    let parameters: Vec<String> = capture
+        .events()
+        .into_iter()
+        .map(|(_, fields)| fields)
+        .filter(|fields| fields.contains("Parameter"))
+        .collect();
+    assert_eq!(parameters.len(), 2, "{:?}", parameters);
+    for fields in &parameters {
+        assert!(
+            fields.contains(&format!("value={}", REDACTED)),
+            "{}",
+            fields
+        );
+    }
+    assert!(parameters
+        .iter()
+        .any(|f| f.contains("path=Device.ManagementServer.PeriodicInformInterval")));
+    assert!(capture
+        .events()
+        .iter()
+        .all(|(_, fields)| !fields.contains("hunter2")));
+}
+
+#[test]
+fn log_level_is_set_per_clone() {
+    let (_nbi, conn, id) = router();
+    let quiet = conn.with_log_level(Level::WARN);
+    let verbose = conn.with_log_level(Level::DEBUG);
+
+    let read = |conn: &acs_api_rs::connection::AcsConnection| {
+        conn.get_parameter_values(id.clone(), vec!["Device.DeviceInfo".to_string()])
+            .unwrap();
+    };
+    let capture_quiet = capture(|| read(&quiet));
+    let capture_verbose = capture(|| read(&verbose));
+
+    assert!(capture_quiet.events().is_empty());
+    assert!(!capture_quiet
+        .spans()
+        .iter()
+        .any(|s| s.starts_with("acs.http")));
+    let levels: Vec<Level> = capture_verbose
+        .events()
+        .into_iter()
+        .map(|(level, _)| level)
+        .collect();
+    assert!(levels.contains(&Level::DEBUG));
+    assert!(!levels.contains(&Level::TRACE));
+    assert!(capture_verbose
+        .spans()
+        .iter()
+        .any(|s| s.starts_with("acs.http")));
+}
@@ -0,0 +1,110 @@
+//! The mock NBI itself: device queries, scripted failures, the request
+//! log and serving over HTTP.
+
+mod common;
+
+use acs_api_rs::acs_type::AcsType;
+use acs_api_rs::connection::AcsConnection;
+use acs_api_rs::mock::{MockNbi, MockRule, MOCK_ADDR, MOCK_FILE_SERVER_ADDR};
+use common::*;
+use reqwest::Method;
+
+#[test]
+fn filters_devices_by_query() {
+    let nbi = MockNbi::new();
+    add_cpe(&nbi, tr181_device("0001"));
+    let gateway = add_cpe(&nbi, tr098_device("0002"));
+    let conn = nbi.connection();
+
+    let devices = conn
+        .list_devices_query(r#"{"_deviceId._ProductClass":"Gateway"}"#)
+        .unwrap();
+
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].id, gateway);
+    assert_eq!(devices[0].device_id.serial_number, "0002");
+}
+
+#[test]
+fn rules_script_failures_a_limited_number_of_times() {
+    let (nbi, conn, _id) = router();
+    nbi.add_rule(MockRule::fail("/devices", 500).method(Method::GET).times(1));
+
+    assert!(conn.list_devices_query("{}").is_err());
+    assert_eq!(conn.list_devices_query("{}").unwrap().len(), 1);
+}
+
+#[test]
+fn logs_received_requests() {
+    let (nbi, conn, id) = router();
+
+    conn.reboot(id.clone()).unwrap();
+
+    let requests = nbi.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, Method::POST);
+    assert_eq!(requests[0].path, format!("/devices/{}/tasks", id));
+    let task: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(task["name"], "reboot");
+}
+
+#[test]
+fn only_the_file_server_serves_file_contents() {
+    let nbi = MockNbi::new();
+    // Named like an NBI collection
+    nbi.add_file(
+        "presets",
+        serde_json::json!({"fileType": "1 Firmware Upgrade Image"}),
+        b"image".to_vec(),
+    );
+    let get = |url: &str| {
+        let response = nbi.handle(Method::GET, url, &Default::default(), Vec::new());
+        return (response.status().as_u16(), response.bytes().unwrap());
+    };
+
+    assert_eq!(get("/files/presets").0, 405);
+    assert_eq!(get(&format!("{}/files/presets", MOCK_ADDR)).0, 405);
+    assert_eq!(get(&format!("{}/presets", MOCK_ADDR)).1, b"[]");
+    assert_eq!(
+        get(&format!("{}/presets", MOCK_FILE_SERVER_ADDR)),
+        (200, b"image".to_vec())
+    );
+    assert_eq!(get(&format!("{}/missing", MOCK_FILE_SERVER_ADDR)).0, 404);
+}
+
+#[test]
+fn serves_over_http() {
+    let (nbi, _conn, id) = router();
+    let server = nbi.serve("127.0.0.1:0").unwrap();
+    let conn = AcsConnection::new(AcsType::GenieAcs, server.addr.clone());
+
+    conn.add_del_tag(id.clone(), true, "lab".to_string())
+        .unwrap();
+
+    assert_eq!(
+        nbi.device(&id).unwrap()["_tags"],
+        serde_json::json!(["lab"])
+    );
+}
+
+#[test]
+fn serves_file_contents_on_a_separate_port() {
+    let nbi = MockNbi::new();
+    nbi.add_file(
+        "fw.bin",
+        serde_json::json!({"fileType": "1 Firmware Upgrade Image"}),
+        b"image".to_vec(),
+    );
+    let server = nbi.serve("127.0.0.1:0").unwrap();
+    let conn = AcsConnection::builder(AcsType::GenieAcs, server.addr.clone())
+        .file_server(&server.file_server_addr)
+        .build()
+        .unwrap();
+
+    let mut content = Vec::new();
+    conn.fetch_file("fw.bin", &mut content, &Default::default())
+        .unwrap();
+
+    assert_eq!(content, b"image");
+    assert_ne!(server.addr, server.file_server_addr);
+}
@@ -0,0 +1,263 @@
+//! The HTTP layer of `AcsConnection` against the mock NBI.
+
+mod common;
+
+use acs_api_rs::acs_type::AcsType;
+use acs_api_rs::connection::AcsConnection;
+use acs_api_rs::fixture::{RecordingTransport, ReplayTransport};
+use acs_api_rs::middleware::Middleware;
+use acs_api_rs::mock::{MockNbi, MockRule, MOCK_ADDR};
+use acs_api_rs::redact::{Redactor, REDACTED};
+use acs_api_rs::retry::RetryPolicy;
+use acs_api_rs::transport::{HttpRequest, HttpResponse, HttpTransport, TransportError};
+use common::*;
+use reqwest::header::AUTHORIZATION;
+use reqwest::Method;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn fast_retries() -> RetryPolicy {
+    return RetryPolicy {
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(1),
+        ..Default::default()
+    };
+}
+
+/// Remembers the URL and `Authorization` header of every request.
+#[derive(Default)]
+struct CaptureAuth {
+    seen: Mutex<Vec<(String, Option<String>)>>,
+}
+
+impl Middleware for CaptureAuth {
+    fn on_request(&self, request: &mut HttpRequest) -> Result<(), Box<dyn std::error::Error>> {
+        let auth = request
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        self.seen.lock().unwrap().push((request.url.clone(), auth));
+        return Ok(());
+    }
+}
+
+#[test]
+fn builder_keeps_nbi_credentials_off_the_file_server() {
+    let nbi = MockNbi::new();
+    nbi.add_file(
+        "fw.bin",
+        serde_json::json!({"fileType": "1 Firmware Upgrade Image"}),
+        b"image".to_vec(),
+    );
+    let capture = Arc::new(CaptureAuth::default());
+    let conn = AcsConnection::builder(AcsType::GenieAcs, MOCK_ADDR.to_string())
+        .transport(Arc::new(nbi.clone()))
+        .basic_auth("nbi", Some("nbi-secret"))
+        .file_server("http://files.example:7567")
+        .file_server_bearer_auth("fs-token")
+        .middleware(capture.clone())
+        .build()
+        .unwrap();
+
+    let mut content = Vec::new();
+    conn.fetch_file("fw.bin", &mut content, &Default::default())
+        .unwrap();
+
+    assert_eq!(content, b"image");
+    let seen = capture.seen.lock().unwrap();
+    let (nbi_requests, fs_requests): (Vec<_>, Vec<_>) =
+        seen.iter().partition(|(url, _)| url.starts_with(MOCK_ADDR));
+    assert!(nbi_requests
+        .iter()
+        .all(|(_, auth)| auth.as_deref().is_some_and(|a| a.starts_with("Basic "))));
+    assert_eq!(fs_requests.len(), 1);
+    assert_eq!(fs_requests[0].0, "http://files.example:7567/fw.bin");
+    assert_eq!(fs_requests[0].1.as_deref(), Some("Bearer fs-token"));
+}
+
+#[test]
+fn retries_transient_failures_of_idempotent_requests() {
+    let (nbi, mut conn, _id) = router();
+    conn.retry_policy = fast_retries();
+    nbi.add_rule(MockRule::fail("/devices", 503).method(Method::GET).times(2));
+
+    let devices = conn.list_devices().unwrap();
+
+    assert_eq!(devices.len(), 1);
+    assert_eq!(nbi.requests().len(), 3);
+}
+
+#[test]
+fn does_not_retry_task_posts() {
+    let (nbi, mut conn, id) = router();
+    conn.retry_policy = fast_retries();
+    nbi.add_rule(
+        MockRule::fail("/devices", 503)
+            .method(Method::POST)
+            .times(1),
+    );
+
+    assert!(conn.reboot(id.clone()).is_err());
+
+    let posts = nbi
+        .requests()
+        .iter()
+        .filter(|r| r.method == Method::POST)
+        .count();
+    assert_eq!(posts, 1);
+    assert_eq!(nbi.cpe(&id).unwrap().boot_count, 0);
+}
+
+#[test]
+fn gives_up_after_max_attempts() {
+    let (nbi, mut conn, _id) = router();
+    conn.retry_policy = RetryPolicy {
+        max_attempts: 2,
+        ..fast_retries()
+    };
+    nbi.add_rule(MockRule::fail("/devices", 503));
+
+    assert!(conn.list_devices().is_err());
+    assert_eq!(nbi.requests().len(), 2);
+}
+
+/// Adds a header and counts responses; refuses DELETE requests.
+#[derive(Default)]
+struct Audit {
+    responses: AtomicUsize,
+}
+
+impl Middleware for Audit {
+    fn on_request(&self, request: &mut HttpRequest) -> Result<(), Box<dyn std::error::Error>> {
+        if request.method == Method::DELETE {
+            return Err(Box::from("Deletes are not allowed"));
+        }
+        request
+            .headers
+            .insert("x-request-id", "test".parse().unwrap());
+        return Ok(());
+    }
+
+    fn on_response(&self, _: &Method, _: &str, _: &HttpResponse, _: Duration) {
+        self.responses.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn middleware_sees_and_can_abort_requests() {
+    let (nbi, conn, id) = router();
+    let audit = Arc::new(Audit::default());
+    let conn = conn.with_middleware(audit.clone());
+
+    conn.list_devices().unwrap();
+    assert_eq!(audit.responses.load(Ordering::SeqCst), 1);
+
+    assert!(conn.del_device(id.clone()).is_err());
+    assert_eq!(nbi.requests().len(), 1);
+    assert!(nbi.device(&id).is_some());
+}
+
+/// Transport counting the requests it forwards to a mock.
+struct Counting {
+    inner: MockNbi,
+    count: AtomicUsize,
+}
+
+impl HttpTransport for Counting {
+    fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        return self.inner.execute(request);
+    }
+}
+
+#[test]
+fn custom_transport_carries_all_requests() {
+    let nbi = MockNbi::new();
+    let id = add_cpe(&nbi, tr181_device("0001"));
+    let transport = Arc::new(Counting {
+        inner: nbi.clone(),
+        count: AtomicUsize::new(0),
+    });
+    let conn =
+        AcsConnection::with_transport(AcsType::GenieAcs, MOCK_ADDR.to_string(), transport.clone());
+
+    conn.list_devices().unwrap();
+    conn.reboot(id).unwrap();
+
+    assert_eq!(transport.count.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn records_and_replays_fixtures_without_secrets() {
+    let (nbi, _conn, id) = router();
+    let redactor = Redactor::new(vec!["*.Password".to_string()]);
+    let recorder =
+        Arc::new(RecordingTransport::new(Arc::new(nbi.clone())).redactor(redactor.clone()));
+    let conn =
+        AcsConnection::with_transport(AcsType::GenieAcs, MOCK_ADDR.to_string(), recorder.clone());
+
+    let recorded = conn
+        .get_parameter_values(id.clone(), vec!["Device.ManagementServer".to_string()])
+        .unwrap();
+    conn.reboot(id.clone()).unwrap();
+
+    let path = temp_dir("fixture").join("session.json");
+    let path = path.to_str().unwrap();
+    recorder.save(path).unwrap();
+    let saved = std::fs::read_to_string(path).unwrap();
+    assert!(!saved.contains("hunter2"));
+    assert!(saved.contains(REDACTED));
+
+    let replay = Arc::new(ReplayTransport::load(path).unwrap().redactor(redactor));
+    let conn =
+        AcsConnection::with_transport(AcsType::GenieAcs, MOCK_ADDR.to_string(), replay.clone());
+    let replayed = conn
+        .get_parameter_values(id.clone(), vec!["Device.ManagementServer".to_string()])
+        .unwrap();
+    assert_eq!(
+        replayed
+            .get_node("Device.ManagementServer.PeriodicInformInterval")
+            .unwrap()
+            .value,
+        recorded
+            .get_node("Device.ManagementServer.PeriodicInformInterval")
+            .unwrap()
+            .value
+    );
+    assert_eq!(
+        replayed
+            .get_node("Device.ManagementServer.Password")
+            .unwrap()
+            .value,
+        REDACTED
+    );
+    assert!(replay.finish().is_err());
+    conn.reboot(id.clone()).unwrap();
+    replay.finish().unwrap();
+
+    // Past the end of the fixture
+    assert!(conn.reboot(id).is_err());
+}
+
+#[test]
+fn redacts_scripts_in_fixtures() {
+    let (nbi, _conn, _id) = router();
+    let recorder = Arc::new(RecordingTransport::new(Arc::new(nbi.clone())));
+    let conn =
+        AcsConnection::with_transport(AcsType::GenieAcs, MOCK_ADDR.to_string(), recorder.clone());
+
+    let script = "declare(\"Device.ManagementServer.Password\", null, {value: \"hunter2\"});";
+    conn.put_provision(&acs_api_rs::provision::AcsProvision::new(
+        "password", script,
+    ))
+    .unwrap();
+
+    let fixture = serde_json::to_string(&recorder.fixture()).unwrap();
+    assert!(!fixture.contains("hunter2"));
+    assert_eq!(
+        nbi.object("provisions", "password").unwrap()["script"],
+        script
+    );
+}